@@ -2,16 +2,32 @@
 
 use std::{env, process};
 
-use proxy_cfg::{ProxyConfig, get_proxy_config};
+use proxy_cfg::{ProxyConfig, ProxyMode, get_proxy_config};
 use url::Url;
 
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
     if args.is_empty() {
         match get_proxy_config() {
+            Ok(Some(ProxyConfig {
+                mode: ProxyMode::Direct,
+                ..
+            })) => println!("Direct connection"),
+            Ok(Some(ProxyConfig {
+                mode: ProxyMode::Unset,
+                diagnostics,
+                ..
+            })) => {
+                println!("No proxy configured");
+                for diagnostic in diagnostics {
+                    println!("warning: {}", diagnostic);
+                }
+            }
             Ok(Some(ProxyConfig { proxies, .. })) => {
-                for (_, p) in proxies {
-                    println!("{}", p);
+                for (_, candidates) in proxies {
+                    for p in candidates {
+                        println!("{}", p);
+                    }
                 }
             }
             Ok(None) => println!("No proxy configured"),
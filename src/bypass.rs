@@ -0,0 +1,672 @@
+//! Parsing and matching of individual bypass list entries.
+
+use std::borrow::Cow;
+use std::net::{IpAddr, Ipv4Addr};
+
+use percent_encoding::percent_decode_str;
+
+use super::BypassDialect;
+
+/// The destination a proxy decision is made for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Target<'a> {
+    /// Lowercase host name or IP address literal.
+    pub(crate) host: &'a str,
+    pub(crate) port: Option<u16>,
+    pub(crate) scheme: Option<&'a str>,
+}
+
+/// How plain domain names in a bypass list match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NameMatching {
+    /// The name itself only.
+    Exact,
+    /// The name and all of its subdomains, with a leading dot being ignored.
+    Suffix,
+    /// The name and all of its subdomains, or its subdomains only when written with a leading dot.
+    SuffixOrSubdomains,
+}
+
+/// The entry syntax understood by a [`BypassDialect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Syntax {
+    pub(crate) names: NameMatching,
+    /// A lone `*` matches every host.
+    pub(crate) match_all: bool,
+    /// `*` and `?` wildcards, in domain names and IPv4 addresses.
+    pub(crate) wildcards: bool,
+    pub(crate) cidr: bool,
+    pub(crate) ports: bool,
+    pub(crate) schemes: bool,
+    /// The `<local>` and `<-loopback>` tokens.
+    pub(crate) tokens: bool,
+}
+
+impl Syntax {
+    /// The syntax of `dialect`. Unless `emulate` is set, only the name matching rules of the dialect apply,
+    /// and every syntax is understood.
+    pub(crate) fn new(dialect: BypassDialect, emulate: bool) -> Syntax {
+        let names = match dialect {
+            BypassDialect::Native | BypassDialect::Windows => NameMatching::Exact,
+            BypassDialect::Curl | BypassDialect::Python | BypassDialect::Wget => NameMatching::Suffix,
+            BypassDialect::Go => NameMatching::SuffixOrSubdomains,
+        };
+        let all = Syntax {
+            names,
+            match_all: true,
+            wildcards: true,
+            cidr: true,
+            ports: true,
+            schemes: true,
+            tokens: true,
+        };
+        let none = Syntax {
+            names,
+            match_all: false,
+            wildcards: false,
+            cidr: false,
+            ports: false,
+            schemes: false,
+            tokens: false,
+        };
+
+        if !emulate {
+            return all;
+        }
+
+        match dialect {
+            BypassDialect::Native => all,
+            BypassDialect::Curl => Syntax {
+                match_all: true,
+                cidr: true,
+                ..none
+            },
+            BypassDialect::Go => Syntax {
+                match_all: true,
+                cidr: true,
+                ports: true,
+                ..none
+            },
+            BypassDialect::Python => Syntax {
+                match_all: true,
+                ports: true,
+                ..none
+            },
+            BypassDialect::Wget => none,
+            BypassDialect::Windows => Syntax {
+                match_all: true,
+                wildcards: true,
+                schemes: true,
+                tokens: true,
+                ..none
+            },
+        }
+    }
+}
+
+/// A bypass list entry: one of the special tokens understood by WinINet and Chromium, or a regular rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BypassEntry<'a> {
+    /// `<local>`: bypass the proxy for simple hostnames, i.e. names without a dot. IP literals are never
+    /// considered simple hostnames.
+    Local,
+    /// `<-loopback>`: remove the implicit loopback bypass. This token never matches a host by itself.
+    SubtractLoopback,
+    Rule(BypassRule<'a>),
+}
+
+impl<'a> BypassEntry<'a> {
+    pub(crate) fn parse(entry: &'a str, syntax: &Syntax) -> BypassEntry<'a> {
+        match entry {
+            "<local>" if syntax.tokens => BypassEntry::Local,
+            "<-loopback>" if syntax.tokens => BypassEntry::SubtractLoopback,
+            _ => BypassEntry::Rule(BypassRule::parse(entry, syntax)),
+        }
+    }
+}
+
+/// A single bypass list entry, split into an optional scheme, its host pattern and an optional port
+/// restriction.
+///
+/// Entries are written as `[scheme://]host[:port]`, where `host` may be a bracketed IPv6 address. An
+/// IPv6 address without brackets is never read as having a port. A scheme or port the syntax does not
+/// understand is left in the host pattern, so that the entry never matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BypassRule<'a> {
+    pub(crate) scheme: Option<&'a str>,
+    pub(crate) host: &'a str,
+    pub(crate) port: Option<u16>,
+}
+
+impl<'a> BypassRule<'a> {
+    pub(crate) fn parse(entry: &'a str, syntax: &Syntax) -> BypassRule<'a> {
+        // Most entries have neither a scheme nor a port, and finding a single byte is cheaper.
+        let separator = match entry.contains(':') {
+            true => entry.split_once("://"),
+            false => None,
+        };
+        let (scheme, entry) = match separator {
+            // A trailing slash is commonly written after a scheme-qualified entry, e.g. "https://github.com/".
+            Some((scheme, rest)) if syntax.schemes => (Some(scheme), rest.strip_suffix('/').unwrap_or(rest)),
+            _ => (None, entry),
+        };
+
+        let (host, port) = match syntax.ports {
+            true => split_host_port(entry),
+            false => (entry, None),
+        };
+
+        BypassRule { scheme, host, port }
+    }
+
+    /// Check whether the scheme and port the rule is limited to, if any, are the ones of `target`.
+    pub(crate) fn permits(&self, target: &Target<'_>) -> bool {
+        // Schemes are case-insensitive, and always lowercase in a parsed URL.
+        self.scheme
+            .is_none_or(|scheme| target.scheme.is_some_and(|target| target.eq_ignore_ascii_case(scheme)))
+            && self.port.is_none_or(|port| target.port == Some(port))
+    }
+}
+
+/// Split a `host[:port]` address, where `host` may be a bracketed IPv6 address. An IPv6 address without brackets
+/// is never read as having a port.
+pub(crate) fn split_host_port(address: &str) -> (&str, Option<u16>) {
+    if let Some((host, port)) = address.rsplit_once(':')
+        && !host.is_empty()
+        && (!host.contains(':') || (host.starts_with('[') && host.ends_with(']')))
+        && let Ok(port) = port.parse()
+    {
+        return (host, Some(port));
+    }
+
+    (address, None)
+}
+
+/// Match a domain name against a curl-style entry: the entry's domain itself or any subdomain of it, with
+/// a leading dot on the entry being ignored.
+pub(crate) fn matches_domain_suffix(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.strip_prefix('.').unwrap_or(pattern);
+    if pattern.is_empty() {
+        return false;
+    }
+
+    host.strip_suffix(pattern)
+        .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+}
+
+/// Check whether a host (lowercase) is a name without any dot, such as `intranet`.
+pub(crate) fn is_simple_hostname(host: &str) -> bool {
+    !host.contains('.') && parse_ip(host).is_none()
+}
+
+/// Check whether a host (lowercase) is covered by the implicit loopback bypass: `localhost` and its
+/// subdomains, `127.0.0.0/8` and `::1`.
+pub(crate) fn is_loopback(host: &str) -> bool {
+    match parse_ip(host) {
+        Some(ip) => ip.to_canonical().is_loopback(),
+        None => matches_domain_suffix("localhost", host.strip_suffix('.').unwrap_or(host)),
+    }
+}
+
+/// An IP network in CIDR notation, e.g. `192.168.16.0/24` or `fe80::/10`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IpCidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpCidr {
+    /// Parse a `address/prefix-length` string. IPv6 addresses may be enclosed in brackets.
+    pub(crate) fn parse(s: &str) -> Option<IpCidr> {
+        let (addr, prefix_len) = s.split_once('/')?;
+        let addr = parse_ip(addr)?;
+        let prefix_len: u8 = prefix_len.parse().ok()?;
+
+        let max_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix_len > max_len {
+            return None;
+        }
+
+        Some(IpCidr { addr, prefix_len })
+    }
+
+    pub(crate) fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(self.prefix_len)).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(self.prefix_len)).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// An IPv4 address pattern with per-octet wildcards, e.g. `192.168.*.*` or `172.16.10.*`.
+///
+/// As with WinINet, a trailing `*` also covers any omitted octets, so `10.*` is the same as `10.*.*.*`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Ipv4Wildcard {
+    octets: [Option<u8>; 4],
+}
+
+impl Ipv4Wildcard {
+    /// Parse a dotted pattern. Returns `None` unless at least one octet is a wildcard.
+    pub(crate) fn parse(s: &str) -> Option<Ipv4Wildcard> {
+        let parts: Vec<&str> = s.split('.').collect();
+        if parts.len() > 4 || (parts.len() < 4 && parts.last() != Some(&"*")) {
+            return None;
+        }
+
+        let mut octets = [None; 4];
+        for (octet, part) in octets.iter_mut().zip(&parts) {
+            *octet = match *part {
+                "*" => None,
+                part if !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) => Some(part.parse().ok()?),
+                _ => return None,
+            };
+        }
+
+        if octets.iter().all(Option::is_some) {
+            return None; // A plain address, not a wildcard.
+        }
+
+        Some(Ipv4Wildcard { octets })
+    }
+
+    pub(crate) fn matches(&self, ip: Ipv4Addr) -> bool {
+        self.octets
+            .iter()
+            .zip(ip.octets())
+            .all(|(pattern, octet)| pattern.is_none_or(|pattern| pattern == octet))
+    }
+}
+
+/// Match a host against a glob pattern where `*` matches any run of characters (including none)
+/// and `?` matches exactly one character. Both strings are expected to be lowercase already.
+pub(crate) fn matches_glob(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let host = host.as_bytes();
+
+    let (mut p, mut h) = (0, 0);
+    // Position of the last `*` seen in the pattern, and the host position it is currently matched up to.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while h < host.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, h));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == host[h] => {
+                p += 1;
+                h += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character and retry from there.
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    h = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Parse an IP address literal, accepting bracketed IPv6 addresses such as `[::1]` and IPv6 zone IDs such as
+/// `fe80::1%eth0` or `fe80::1%25eth0`. The zone ID is ignored.
+pub(crate) fn parse_ip(s: &str) -> Option<IpAddr> {
+    let s = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')).unwrap_or(s);
+    let s = match s.split_once('%') {
+        Some((addr, _zone)) if addr.contains(':') => addr,
+        _ => s,
+    };
+    s.parse().ok()
+}
+
+/// Normalize a target host for matching: domain names in their canonical ASCII (punycode) form, and IP
+/// literals in their canonical textual form, without brackets or zone ID. IPv4-mapped IPv6 addresses are
+/// written as plain IPv4 addresses.
+pub(crate) fn normalize_host(host: &str) -> String {
+    match parse_ip(host) {
+        Some(ip) => ip.to_canonical().to_string(),
+        None => normalize_domain(host).into_owned(),
+    }
+}
+
+/// Convert a domain name, or a host pattern, to the lowercase ASCII form produced by `url::Url` for
+/// internationalized domain names, e.g. `Bücher.example` to `xn--bcher-kva.example`. Percent-encoded
+/// names, as found in the host of URLs with a non-special scheme, are decoded first.
+///
+/// Labels of a pattern containing a wildcard are converted one by one, and a label that is not a valid
+/// domain label is only lowercased.
+///
+/// The trailing dot of an absolute name is removed, so that `intranet.corp.` and `intranet.corp` are equal.
+/// It is kept when nothing but wildcards would remain, so that `*.` does not become `*` and match every host.
+pub(crate) fn normalize_domain(domain: &str) -> Cow<'_, str> {
+    let domain = domain
+        .strip_suffix('.')
+        .filter(|domain| !domain.bytes().all(|b| matches!(b, b'*' | b'?')))
+        .unwrap_or(domain);
+
+    // The common case of a name that is already normalized, checked without branching on every byte.
+    let special = domain.bytes().fold(false, |special, b| {
+        special | !b.is_ascii() | b.is_ascii_uppercase() | (b == b'%')
+    });
+    if !special {
+        return Cow::Borrowed(domain);
+    }
+
+    if domain.contains('%') {
+        let decoded = percent_decode_str(domain).decode_utf8_lossy();
+        if decoded != domain {
+            return Cow::Owned(normalize_domain(&decoded).into_owned());
+        }
+    }
+
+    if domain.is_ascii() {
+        return match domain.bytes().any(|b| b.is_ascii_uppercase()) {
+            true => Cow::Owned(domain.to_ascii_lowercase()),
+            false => Cow::Borrowed(domain),
+        };
+    }
+
+    if !domain.contains(['*', '?'])
+        && let Ok(ascii) = idna::domain_to_ascii(domain)
+    {
+        return Cow::Owned(ascii);
+    }
+
+    let labels: Vec<String> = domain
+        .split('.')
+        .map(|label| match label.contains(['*', '?']) {
+            false => idna::domain_to_ascii(label).unwrap_or_else(|_| label.to_lowercase()),
+            true => label.to_lowercase(),
+        })
+        .collect();
+    Cow::Owned(labels.join("."))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::{
+        BypassDialect, BypassEntry, BypassRule, IpCidr, Ipv4Wildcard, Syntax, is_loopback, is_simple_hostname,
+        matches_domain_suffix, matches_glob, normalize_domain, normalize_host, parse_ip,
+    };
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_bypass_entry() {
+        let native = Syntax::new(BypassDialect::Native, false);
+        assert_eq!(BypassEntry::parse("<local>", &native), BypassEntry::Local);
+        assert_eq!(
+            BypassEntry::parse("<-loopback>", &native),
+            BypassEntry::SubtractLoopback
+        );
+        assert_eq!(
+            BypassEntry::parse("localhost", &native),
+            BypassEntry::Rule(BypassRule::parse("localhost", &native))
+        );
+    }
+
+    #[test]
+    fn test_is_simple_hostname() {
+        assert!(is_simple_hostname("intranet"));
+        assert!(!is_simple_hostname("intranet.corp"));
+        assert!(!is_simple_hostname("fe80::1"));
+    }
+
+    #[test]
+    fn test_is_loopback() {
+        assert!(is_loopback("localhost"));
+        assert!(is_loopback("localhost."));
+        assert!(is_loopback("app.localhost"));
+        assert!(is_loopback("127.0.0.1"));
+        assert!(is_loopback("127.12.34.56"));
+        assert!(is_loopback("::1"));
+        assert!(is_loopback("[::1]"));
+        assert!(is_loopback("::ffff:127.0.0.1"));
+
+        assert!(!is_loopback("localhost.example.com"));
+        assert!(!is_loopback("mylocalhost"));
+        assert!(!is_loopback("128.0.0.1"));
+        assert!(!is_loopback("::2"));
+    }
+
+    #[test]
+    fn test_parse_bypass_rule() {
+        let native = Syntax::new(BypassDialect::Native, false);
+        let rule = |scheme, host, port| BypassRule { scheme, host, port };
+
+        assert_eq!(
+            BypassRule::parse("example.com", &native),
+            rule(None, "example.com", None)
+        );
+        assert_eq!(
+            BypassRule::parse("example.com:8443", &native),
+            rule(None, "example.com", Some(8443))
+        );
+        assert_eq!(
+            BypassRule::parse("*.example.com:80", &native),
+            rule(None, "*.example.com", Some(80))
+        );
+        assert_eq!(
+            BypassRule::parse("10.0.0.1:8080", &native),
+            rule(None, "10.0.0.1", Some(8080))
+        );
+        assert_eq!(
+            BypassRule::parse("[::1]:8080", &native),
+            rule(None, "[::1]", Some(8080))
+        );
+        assert_eq!(BypassRule::parse("[::1]", &native), rule(None, "[::1]", None));
+        assert_eq!(BypassRule::parse("::1", &native), rule(None, "::1", None));
+        assert_eq!(BypassRule::parse("fe80::/10", &native), rule(None, "fe80::/10", None));
+        assert_eq!(
+            BypassRule::parse("example.com:http", &native),
+            rule(None, "example.com:http", None)
+        );
+        assert_eq!(
+            BypassRule::parse("example.com:99999", &native),
+            rule(None, "example.com:99999", None)
+        );
+        assert_eq!(BypassRule::parse(":80", &native), rule(None, ":80", None));
+
+        assert_eq!(
+            BypassRule::parse("https://github.com/", &native),
+            rule(Some("https"), "github.com", None)
+        );
+        assert_eq!(
+            BypassRule::parse("http://internal", &native),
+            rule(Some("http"), "internal", None)
+        );
+        assert_eq!(
+            BypassRule::parse("http://[::1]:81", &native),
+            rule(Some("http"), "[::1]", Some(81))
+        );
+        assert_eq!(
+            BypassRule::parse("http://10.0.0.0/8", &native),
+            rule(Some("http"), "10.0.0.0/8", None)
+        );
+
+        // Schemes and ports the dialect does not understand stay in the host pattern.
+        let curl = Syntax::new(BypassDialect::Curl, true);
+        assert_eq!(
+            BypassRule::parse("example.com:8443", &curl),
+            rule(None, "example.com:8443", None)
+        );
+        assert_eq!(
+            BypassRule::parse("https://github.com/", &curl),
+            rule(None, "https://github.com/", None)
+        );
+        assert_eq!(
+            BypassEntry::parse("<local>", &curl),
+            BypassEntry::Rule(rule(None, "<local>", None))
+        );
+    }
+
+    #[test]
+    fn test_matches_domain_suffix() {
+        assert!(matches_domain_suffix("example.com", "example.com"));
+        assert!(matches_domain_suffix(".example.com", "www.example.com"));
+        assert!(!matches_domain_suffix("example.com", "badexample.com"));
+        assert!(!matches_domain_suffix(".", "example.com"));
+        assert!(!matches_domain_suffix("", "example.com"));
+    }
+
+    #[test]
+    fn test_parse_cidr() {
+        assert!(IpCidr::parse("192.168.16.0/24").is_some());
+        assert!(IpCidr::parse("10.0.0.0/8").is_some());
+        assert!(IpCidr::parse("0.0.0.0/0").is_some());
+        assert!(IpCidr::parse("fe80::/10").is_some());
+        assert!(IpCidr::parse("[fd00::]/8").is_some());
+
+        assert!(IpCidr::parse("192.168.16.0").is_none());
+        assert!(IpCidr::parse("192.168.16.0/33").is_none());
+        assert!(IpCidr::parse("::/129").is_none());
+        assert!(IpCidr::parse("example.com/24").is_none());
+        assert!(IpCidr::parse("192.168.16.0/").is_none());
+    }
+
+    #[test]
+    fn test_cidr_contains() {
+        let net = IpCidr::parse("192.168.16.0/24").unwrap();
+        assert!(net.contains(ip("192.168.16.0")));
+        assert!(net.contains(ip("192.168.16.255")));
+        assert!(!net.contains(ip("192.168.17.1")));
+        assert!(!net.contains(ip("::ffff:192.168.16.1")));
+
+        let net = IpCidr::parse("0.0.0.0/0").unwrap();
+        assert!(net.contains(ip("8.8.8.8")));
+
+        let net = IpCidr::parse("10.1.2.3/32").unwrap();
+        assert!(net.contains(ip("10.1.2.3")));
+        assert!(!net.contains(ip("10.1.2.4")));
+
+        let net = IpCidr::parse("fe80::/10").unwrap();
+        assert!(net.contains(ip("fe80::1")));
+        assert!(net.contains(ip("febf::1")));
+        assert!(!net.contains(ip("fec0::1")));
+        assert!(!net.contains(ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn test_parse_ipv4_wildcard() {
+        assert!(Ipv4Wildcard::parse("192.168.*.*").is_some());
+        assert!(Ipv4Wildcard::parse("172.16.10.*").is_some());
+        assert!(Ipv4Wildcard::parse("*.*.*.1").is_some());
+        assert!(Ipv4Wildcard::parse("10.*").is_some());
+        assert!(Ipv4Wildcard::parse("192.168.*").is_some());
+
+        assert!(Ipv4Wildcard::parse("192.168.1.1").is_none());
+        assert!(Ipv4Wildcard::parse("192.168.1").is_none());
+        assert!(Ipv4Wildcard::parse("192.168.256.*").is_none());
+        assert!(Ipv4Wildcard::parse("192.168.1*.*").is_none());
+        assert!(Ipv4Wildcard::parse("*.example.com").is_none());
+        assert!(Ipv4Wildcard::parse("1.2.3.4.*").is_none());
+        assert!(Ipv4Wildcard::parse("192..*.*").is_none());
+    }
+
+    #[test]
+    fn test_ipv4_wildcard_matches() {
+        let wildcard = Ipv4Wildcard::parse("192.168.*.*").unwrap();
+        assert!(wildcard.matches("192.168.0.1".parse().unwrap()));
+        assert!(wildcard.matches("192.168.255.255".parse().unwrap()));
+        assert!(!wildcard.matches("192.169.0.1".parse().unwrap()));
+
+        let wildcard = Ipv4Wildcard::parse("172.16.10.*").unwrap();
+        assert!(wildcard.matches("172.16.10.7".parse().unwrap()));
+        assert!(!wildcard.matches("172.16.11.7".parse().unwrap()));
+
+        let wildcard = Ipv4Wildcard::parse("10.*").unwrap();
+        assert!(wildcard.matches("10.1.2.3".parse().unwrap()));
+        assert!(!wildcard.matches("11.1.2.3".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_matches_glob() {
+        assert!(matches_glob("*.example.com", "sub.example.com"));
+        assert!(matches_glob("*.example.com", "a.b.example.com"));
+        assert!(!matches_glob("*.example.com", "example.com"));
+        assert!(!matches_glob("*.example.com", "example.com.evil"));
+
+        assert!(matches_glob("intranet*", "intranet"));
+        assert!(matches_glob("intranet*", "intranet.corp"));
+        assert!(!matches_glob("intranet*", "myintranet"));
+
+        assert!(matches_glob("*test*.com", "test.com"));
+        assert!(matches_glob("*test*.com", "my-tests.example.com"));
+        assert!(!matches_glob("*test*.com", "example.com"));
+        assert!(!matches_glob("*test*.com", "test.com.au"));
+
+        assert!(matches_glob("host?.corp", "host1.corp"));
+        assert!(!matches_glob("host?.corp", "host.corp"));
+        assert!(!matches_glob("host?.corp", "host12.corp"));
+
+        assert!(matches_glob("a*b*c", "abc"));
+        assert!(matches_glob("a*b*c", "aXbXbXc"));
+        assert!(!matches_glob("a*b*c", "aXbXcX"));
+        assert!(matches_glob("**", ""));
+        assert!(matches_glob("exact.host", "exact.host"));
+        assert!(!matches_glob("exact.host", "exact.hostname"));
+    }
+
+    #[test]
+    fn test_parse_ip() {
+        assert_eq!(parse_ip("127.0.0.1"), Some(ip("127.0.0.1")));
+        assert_eq!(parse_ip("::1"), Some(ip("::1")));
+        assert_eq!(parse_ip("[::1]"), Some(ip("::1")));
+        assert_eq!(parse_ip("fe80::1%eth0"), Some(ip("fe80::1")));
+        assert_eq!(parse_ip("[fe80::1%25eth0]"), Some(ip("fe80::1")));
+        assert_eq!(parse_ip("127.0.0.1%eth0"), None);
+        assert_eq!(parse_ip("localhost"), None);
+    }
+
+    #[test]
+    fn test_normalize_domain() {
+        assert_eq!(normalize_domain("example.com"), "example.com");
+        assert_eq!(normalize_domain("Example.COM"), "example.com");
+        assert_eq!(normalize_domain("Bücher.example"), "xn--bcher-kva.example");
+        assert_eq!(normalize_domain("bücher.example"), "xn--bcher-kva.example");
+        assert_eq!(normalize_domain("xn--bcher-kva.example"), "xn--bcher-kva.example");
+        assert_eq!(normalize_domain("b%C3%BCcher.example"), "xn--bcher-kva.example");
+        assert_eq!(normalize_domain("MÜNCHEN.de"), "xn--mnchen-3ya.de");
+        assert_eq!(normalize_domain("*.münchen.de"), "*.xn--mnchen-3ya.de");
+        assert_eq!(normalize_domain("*.MÜNCHEN.de"), "*.xn--mnchen-3ya.de");
+        assert_eq!(normalize_domain(".münchen.de"), ".xn--mnchen-3ya.de");
+        assert_eq!(normalize_domain("bü*.de"), "bü*.de");
+        assert_eq!(normalize_domain("intranet.corp."), "intranet.corp");
+        assert_eq!(normalize_domain("Intranet.Corp."), "intranet.corp");
+        assert_eq!(normalize_domain("bücher.example."), "xn--bcher-kva.example");
+        assert_eq!(normalize_domain("*.corp."), "*.corp");
+        assert_eq!(normalize_domain("."), ".");
+        assert_eq!(normalize_domain("*."), "*.");
+        assert_eq!(normalize_domain("?."), "?.");
+    }
+
+    #[test]
+    fn test_normalize_host() {
+        assert_eq!(normalize_host("Example.COM"), "example.com");
+        assert_eq!(normalize_host("Bücher.example"), "xn--bcher-kva.example");
+        assert_eq!(normalize_host("[::1]"), "::1");
+        assert_eq!(normalize_host("[0:0:0:0:0:0:0:1]"), "::1");
+        assert_eq!(normalize_host("FE80::ABCD%eth0"), "fe80::abcd");
+        assert_eq!(normalize_host("[::ffff:192.168.1.1]"), "192.168.1.1");
+        assert_eq!(normalize_host("10.0.0.1"), "10.0.0.1");
+    }
+}
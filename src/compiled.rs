@@ -0,0 +1,164 @@
+//! A proxy configuration with its bypass list compiled, for making many proxy decisions.
+
+use url::Url;
+
+use super::{BypassMatcher, ProxyCandidate, ProxyConfig, ProxyDecision, ProxyEndpoint, ProxySelector, Resolver};
+
+/// A [`ProxyConfig`] with its bypass list compiled into a [`BypassMatcher`], as returned by
+/// [`ProxyConfig::compile`].
+///
+/// Decisions are the same as the ones made by the methods of [`ProxyConfig`], which match the whitelist entry
+/// by entry. [`CompiledProxyConfig::get_proxy_for_url`] only allocates for the returned endpoint.
+#[derive(Debug, Clone)]
+pub struct CompiledProxyConfig {
+    config: ProxyConfig,
+    matcher: BypassMatcher,
+}
+
+impl CompiledProxyConfig {
+    pub fn new(config: ProxyConfig) -> CompiledProxyConfig {
+        CompiledProxyConfig {
+            matcher: BypassMatcher::new(&config),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &ProxyConfig {
+        &self.config
+    }
+
+    pub fn matcher(&self) -> &BypassMatcher {
+        &self.matcher
+    }
+
+    /// See [`ProxyConfig::use_proxy_for_address`].
+    pub fn use_proxy_for_address(&self, address: &str) -> bool {
+        self.matcher.use_proxy_for_address(address)
+    }
+
+    /// See [`ProxyConfig::get_proxy_for_url`].
+    pub fn get_proxy_for_url(&self, url: &Url) -> Option<ProxyEndpoint> {
+        if !self.matcher.use_proxy_for_url(url) {
+            return None;
+        }
+
+        let (_, candidates) = self.config.candidates_for_scheme(url.scheme())?;
+        candidates.first().and_then(ProxyCandidate::endpoint).cloned()
+    }
+
+    /// See [`ProxyConfig::get_proxy_candidates_for_url`].
+    pub fn get_proxy_candidates_for_url(&self, url: &Url) -> Vec<ProxyCandidate> {
+        let candidates = match self.matcher.use_proxy_for_url(url) {
+            true => self.config.candidates_for_scheme(url.scheme()),
+            false => None,
+        };
+
+        match candidates {
+            Some((_, candidates)) => candidates.to_vec(),
+            None => vec![ProxyCandidate::Direct],
+        }
+    }
+
+    /// See [`ProxyConfig::select_proxy_for_url`].
+    pub fn select_proxy_for_url(&self, url: &Url, selector: &ProxySelector) -> Option<ProxyEndpoint> {
+        let candidates = self.get_proxy_candidates_for_url(url);
+        selector
+            .select(&candidates, url)
+            .and_then(ProxyCandidate::endpoint)
+            .cloned()
+    }
+
+    /// See [`ProxyConfig::explain_proxy_for_url`].
+    pub fn explain_proxy_for_url(&self, url: &Url) -> ProxyDecision {
+        self.config.decide(url, self.matcher.bypass_reason(url))
+    }
+
+    /// See [`ProxyConfig::use_proxy_for_address_with_resolver`].
+    pub fn use_proxy_for_address_with_resolver<R>(&self, address: &str, resolver: &R) -> bool
+    where
+        R: Resolver + ?Sized,
+    {
+        self.matcher.use_proxy_for_address_with_resolver(address, resolver)
+    }
+
+    /// See [`ProxyConfig::get_proxy_for_url_with_resolver`].
+    pub fn get_proxy_for_url_with_resolver<R>(&self, url: &Url, resolver: &R) -> Option<ProxyEndpoint>
+    where
+        R: Resolver + ?Sized,
+    {
+        self.explain_proxy_for_url_with_resolver(url, resolver).proxy
+    }
+
+    /// See [`ProxyConfig::explain_proxy_for_url_with_resolver`].
+    pub fn explain_proxy_for_url_with_resolver<R>(&self, url: &Url, resolver: &R) -> ProxyDecision
+    where
+        R: Resolver + ?Sized,
+    {
+        self.config
+            .decide(url, self.matcher.bypass_reason_with_resolver(url, resolver))
+    }
+}
+
+impl From<ProxyConfig> for CompiledProxyConfig {
+    fn from(config: ProxyConfig) -> Self {
+        CompiledProxyConfig::new(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::{ProxyCandidate, ProxyConfig, ProxyEndpoint, ProxyMode};
+
+    fn endpoint(value: &str) -> ProxyEndpoint {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_compiled_decisions() {
+        let mut config = ProxyConfig::default();
+        config.proxies.insert(
+            "http".to_owned(),
+            vec![endpoint("1.1.1.1").into(), ProxyCandidate::Direct],
+        );
+        config.proxies.insert("*".to_owned(), vec![endpoint("2.2.2.2").into()]);
+        config.whitelist.insert("*.example.com".to_owned());
+        config.exclude_simple = true;
+        let compiled = config.compile();
+
+        for url in [
+            "http://example.org",
+            "http://www.example.com",
+            "ftp://example.org",
+            "http://intranet",
+            "https://127.0.0.1",
+        ] {
+            let url = Url::parse(url).unwrap();
+            assert_eq!(
+                compiled.get_proxy_for_url(&url),
+                config.get_proxy_for_url(&url),
+                "{url}"
+            );
+            assert_eq!(
+                compiled.get_proxy_candidates_for_url(&url),
+                config.get_proxy_candidates_for_url(&url),
+                "{url}"
+            );
+            assert_eq!(
+                compiled.explain_proxy_for_url(&url),
+                config.explain_proxy_for_url(&url),
+                "{url}"
+            );
+        }
+
+        config.mode = ProxyMode::Direct;
+        let compiled = config.compile();
+        let url = Url::parse("http://example.org").unwrap();
+        assert_eq!(compiled.get_proxy_for_url(&url), None);
+        assert_eq!(
+            compiled.get_proxy_candidates_for_url(&url),
+            vec![ProxyCandidate::Direct]
+        );
+    }
+}
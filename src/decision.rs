@@ -0,0 +1,113 @@
+//! The outcome of a proxy decision, with the reasons it was made.
+
+use std::fmt;
+use std::net::IpAddr;
+
+use super::{ConfigSource, ProxyCandidate, ProxyEndpoint, ProxyMode};
+
+/// Why a destination bypasses the proxy.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BypassReason {
+    /// The host is a simple hostname (no dots) and [`ProxyConfig::exclude_simple`](crate::ProxyConfig) is set.
+    ExcludeSimple,
+    /// The host is a simple hostname (no dots) and the whitelist contains `<local>`.
+    Local,
+    /// The host is a loopback name or address, see [`LoopbackPolicy`](crate::LoopbackPolicy).
+    Loopback,
+    /// The host matched this whitelist entry, the first one in byte order when several did.
+    Rule(String),
+    /// The host resolved to `address`, which matched this whitelist entry.
+    Resolved { address: IpAddr, entry: String },
+}
+
+impl fmt::Display for BypassReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BypassReason::ExcludeSimple => write!(f, "simple hostnames are excluded"),
+            BypassReason::Local => write!(f, "simple hostnames are excluded by the \"<local>\" entry"),
+            BypassReason::Loopback => write!(f, "loopback destinations are never proxied"),
+            BypassReason::Rule(entry) => write!(f, "matched bypass entry \"{}\"", entry),
+            BypassReason::Resolved { address, entry } => {
+                write!(
+                    f,
+                    "host resolved to {}, which matched bypass entry \"{}\"",
+                    address, entry
+                )
+            }
+        }
+    }
+}
+
+/// The key of [`ProxyConfig::proxies`](crate::ProxyConfig) a proxy was found under.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ProxyKey {
+    /// The URL's own scheme, e.g. `https`.
+    Scheme(String),
+    /// The `*` entry, used for schemes without a proxy of their own.
+    Fallback,
+}
+
+impl fmt::Display for ProxyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProxyKey::Scheme(scheme) => write!(f, "scheme \"{}\"", scheme),
+            ProxyKey::Fallback => write!(f, "the \"*\" fallback"),
+        }
+    }
+}
+
+/// A proxy decision for a URL, as returned by
+/// [`ProxyConfig::explain_proxy_for_url`](crate::ProxyConfig::explain_proxy_for_url).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ProxyDecision {
+    /// The proxy to use, or `None` to connect directly. This is the first of the candidates.
+    pub proxy: Option<ProxyEndpoint>,
+    /// All of the ways to reach the URL, in the configured order.
+    pub candidates: Vec<ProxyCandidate>,
+    /// The key the proxy was found under. `None` when the proxy is bypassed or no proxy is configured for
+    /// the URL's scheme.
+    pub proxy_key: Option<ProxyKey>,
+    /// Why the proxy is bypassed, or `None` if no bypass rule applies.
+    pub bypass: Option<BypassReason>,
+    /// The URL's scheme.
+    pub scheme: String,
+    /// Where the configuration was read from.
+    pub source: ConfigSource,
+    /// The mode of the configuration, which tells why there is no proxy for a URL that no bypass rule matched.
+    pub mode: ProxyMode,
+}
+
+impl fmt::Display for ProxyDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.proxy, &self.proxy_key, &self.bypass) {
+            (Some(proxy), Some(key), _) => write!(f, "use proxy {} configured for {}", proxy, key)?,
+            (_, _, Some(reason)) => write!(f, "connect directly: {}", reason)?,
+            (None, Some(key), None) => write!(f, "connect directly as configured for {}", key)?,
+            _ if self.mode == ProxyMode::Direct => {
+                return write!(
+                    f,
+                    "connect directly: proxies are explicitly disabled by {}",
+                    self.source
+                );
+            }
+            _ if self.mode == ProxyMode::AutoConfig => write!(
+                f,
+                "connect directly: proxy chosen by a PAC script, which is not evaluated"
+            )?,
+            _ if self.mode == ProxyMode::AutoDetect => write!(
+                f,
+                "connect directly: proxy chosen by a PAC script found through WPAD, which is not evaluated"
+            )?,
+            _ => write!(
+                f,
+                "connect directly: no proxy configured for scheme \"{}\"",
+                self.scheme
+            )?,
+        }
+
+        write!(f, " (source: {})", self.source)
+    }
+}
@@ -7,7 +7,7 @@ use core_foundation::number::CFNumber;
 use core_foundation::string::{CFString, CFStringRef};
 use system_configuration_sys::dynamic_store_copy_specific;
 
-use super::{ProxyConfig, Result};
+use super::{BypassDialect, ConfigSource, Diagnostic, ProxyConfig, ProxyEndpoint, ProxyMode, ProxyProtocol, Result};
 
 fn get_array_value(dictionary: &CFDictionary<CFString, CFType>, key: &'static str) -> Option<CFArray> {
     let key = CFString::from_static_string(key);
@@ -34,7 +34,11 @@ pub(crate) fn get_proxy_config() -> Result<Option<ProxyConfig>> {
     // SAFETY: SCDynamicStoreCopyProxies is a system API that returns a CFDictionary reference or null.
     let proxies_ref = unsafe { dynamic_store_copy_specific::SCDynamicStoreCopyProxies(ptr::null()) };
 
-    let mut proxy_config: ProxyConfig = Default::default();
+    let mut proxy_config = ProxyConfig {
+        source: ConfigSource::MacOs,
+        dialect: BypassDialect::Native,
+        ..Default::default()
+    };
 
     if proxies_ref.is_null() {
         return Ok(None);
@@ -43,13 +47,30 @@ pub(crate) fn get_proxy_config() -> Result<Option<ProxyConfig>> {
     // SAFETY: proxies_ref is non-null (checked above) and was created by SCDynamicStoreCopyProxies with "Create" semantics.
     let proxies: CFDictionary<CFString, CFType> = unsafe { CFDictionary::wrap_under_create_rule(proxies_ref) };
 
+    if get_i32_value(&proxies, "ProxyAutoConfigEnable").unwrap_or(0) == 1 {
+        proxy_config.mode = ProxyMode::AutoConfig;
+        return Ok(Some(proxy_config));
+    }
+
+    if get_i32_value(&proxies, "ProxyAutoDiscoveryEnable").unwrap_or(0) == 1 {
+        proxy_config.mode = ProxyMode::AutoDetect;
+        return Ok(Some(proxy_config));
+    }
+
     if get_i32_value(&proxies, "HTTPEnable").unwrap_or(0) == 1 {
         let mut url = get_string_value(&proxies, "HTTPProxy").unwrap_or_default();
         if let Some(port) = get_i32_value(&proxies, "HTTPPort") {
             url = format!("{}:{}", url, port);
         }
 
-        proxy_config.proxies.insert("http".into(), url);
+        match url.parse::<ProxyEndpoint>() {
+            Ok(proxy) => {
+                proxy_config.proxies.insert("http".into(), vec![proxy.into()]);
+            }
+            Err(_) => proxy_config
+                .diagnostics
+                .push(Diagnostic::invalid_proxy("HTTPProxy", &url)),
+        }
     }
 
     if get_i32_value(&proxies, "HTTPSEnable").unwrap_or(0) == 1 {
@@ -58,7 +79,14 @@ pub(crate) fn get_proxy_config() -> Result<Option<ProxyConfig>> {
             url = format!("{}:{}", url, port);
         }
 
-        proxy_config.proxies.insert("https".into(), url);
+        match url.parse::<ProxyEndpoint>() {
+            Ok(proxy) => {
+                proxy_config.proxies.insert("https".into(), vec![proxy.into()]);
+            }
+            Err(_) => proxy_config
+                .diagnostics
+                .push(Diagnostic::invalid_proxy("HTTPSProxy", &url)),
+        }
     }
 
     if get_i32_value(&proxies, "FTPEnable").unwrap_or(0) == 1 {
@@ -67,12 +95,41 @@ pub(crate) fn get_proxy_config() -> Result<Option<ProxyConfig>> {
             url = format!("{}:{}", url, port);
         }
 
-        proxy_config.proxies.insert("ftp".into(), url);
+        match url.parse::<ProxyEndpoint>() {
+            Ok(proxy) => {
+                proxy_config.proxies.insert("ftp".into(), vec![proxy.into()]);
+            }
+            Err(_) => proxy_config
+                .diagnostics
+                .push(Diagnostic::invalid_proxy("FTPProxy", &url)),
+        }
         // TODO: kSCPropNetProxiesFTPPassive.
     }
 
+    if get_i32_value(&proxies, "SOCKSEnable").unwrap_or(0) == 1 {
+        let mut url = get_string_value(&proxies, "SOCKSProxy").unwrap_or_default();
+        if let Some(port) = get_i32_value(&proxies, "SOCKSPort") {
+            url = format!("{}:{}", url, port);
+        }
+
+        match ProxyEndpoint::parse_with_protocol(&url, ProxyProtocol::Socks5) {
+            Ok(proxy) => {
+                proxy_config.proxies.insert("*".into(), vec![proxy.into()]);
+            }
+            Err(_) => proxy_config
+                .diagnostics
+                .push(Diagnostic::invalid_proxy("SOCKSProxy", &url)),
+        }
+    }
+
+    // The network settings always hold an answer: with no proxy enabled, connections are direct. Enabled proxies
+    // that are not valid are reported instead.
     if proxy_config.proxies.is_empty() {
-        return Ok(None);
+        proxy_config.mode = match proxy_config.diagnostics.is_empty() {
+            true => ProxyMode::Direct,
+            false => ProxyMode::Unset,
+        };
+        return Ok(Some(proxy_config));
     }
 
     if get_i32_value(&proxies, "ExcludeSimpleHostnames").unwrap_or(0) == 1 {
@@ -0,0 +1,1021 @@
+//! A bypass list compiled into lookup tables, for making many proxy decisions against the same
+//! [`ProxyConfig`], and the same bypass list matched entry by entry, for one-off decisions.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+use std::net::IpAddr;
+
+use url::{Host, Url};
+
+use super::bypass::{self, BypassEntry, BypassRule, IpCidr, Ipv4Wildcard, NameMatching, Syntax, Target};
+use super::{BypassReason, LoopbackPolicy, ProxyConfig, Resolver};
+
+/// The whitelist entry a table entry was compiled from, and the scheme and port it is limited to, if any.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Restriction {
+    entry: String,
+    /// The position of the entry in the whitelist sorted in byte order. When several entries match, the first
+    /// one is reported, whatever the iteration order of the whitelist.
+    rank: usize,
+    scheme: Option<String>,
+    port: Option<u16>,
+}
+
+impl Restriction {
+    fn new(entry: &str, rank: usize, rule: &BypassRule<'_>) -> Restriction {
+        Restriction {
+            entry: entry.to_owned(),
+            rank,
+            // Schemes are case-insensitive, and always lowercase in a parsed URL.
+            scheme: rule.scheme.map(str::to_ascii_lowercase),
+            port: rule.port,
+        }
+    }
+
+    fn permits(&self, target: &Target<'_>) -> bool {
+        self.scheme
+            .as_deref()
+            .is_none_or(|scheme| target.scheme == Some(scheme))
+            && self.port.is_none_or(|port| target.port == Some(port))
+    }
+}
+
+/// The first restriction of `restrictions`, which are in rank order, permitting `target`.
+fn find_permitted<'a>(restrictions: &'a [Restriction], target: &Target<'_>) -> Option<&'a Restriction> {
+    restrictions.iter().find(|restriction| restriction.permits(target))
+}
+
+/// The restriction of the first entry in the whitelist among `found`.
+fn first_ranked<'a>(found: impl IntoIterator<Item = Option<&'a Restriction>>) -> Option<&'a Restriction> {
+    found.into_iter().flatten().min_by_key(|restriction| restriction.rank)
+}
+
+/// A borrowed [`BypassReason`], so that lookups do not allocate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bypass<'a> {
+    ExcludeSimple,
+    Local,
+    Loopback,
+    Rule(&'a str),
+    Resolved(IpAddr, &'a str),
+}
+
+impl From<Bypass<'_>> for BypassReason {
+    fn from(bypass: Bypass<'_>) -> Self {
+        match bypass {
+            Bypass::ExcludeSimple => BypassReason::ExcludeSimple,
+            Bypass::Local => BypassReason::Local,
+            Bypass::Loopback => BypassReason::Loopback,
+            Bypass::Rule(entry) => BypassReason::Rule(entry.to_owned()),
+            Bypass::Resolved(address, entry) => BypassReason::Resolved {
+                address,
+                entry: entry.to_owned(),
+            },
+        }
+    }
+}
+
+/// A trie of domain names keyed by their labels, starting from the top-level domain.
+#[derive(Debug, Clone, Default)]
+struct DomainTrie {
+    children: HashMap<String, DomainTrie>,
+    /// Rules matching this domain and all of its subdomains (curl-style entries).
+    domain: Vec<Restriction>,
+    /// Rules matching the subdomains of this domain only (`*.example.com`, or `.example.com` for Go).
+    subdomains: Vec<Restriction>,
+}
+
+impl DomainTrie {
+    fn node_mut(&mut self, domain: &str) -> &mut DomainTrie {
+        domain
+            .rsplit('.')
+            .fold(self, |node, label| node.children.entry(label.to_owned()).or_default())
+    }
+
+    fn find(&self, host: &str, target: &Target<'_>) -> Option<&Restriction> {
+        let mut node = self;
+        let mut labels = host.rsplit('.').peekable();
+        let mut found = None;
+
+        while let Some(label) = labels.next() {
+            let Some(child) = node.children.get(label) else {
+                break;
+            };
+            node = child;
+
+            let subdomains = match labels.peek() {
+                Some(_) => find_permitted(&node.subdomains, target),
+                None => None,
+            };
+            found = first_ranked([found, find_permitted(&node.domain, target), subdomains]);
+        }
+
+        found
+    }
+}
+
+/// The host pattern of a rule, normalized and read with the syntax of the dialect. The IP forms of the pattern
+/// are only parsed when asked for, so that matching a host name against it is cheap.
+struct HostPattern<'a> {
+    normalized: Cow<'a, str>,
+    /// The length of the prefix of `normalized` to skip: Go reads `*.example.com` as `.example.com`.
+    skip: usize,
+    /// The pattern holds wildcards the syntax understands.
+    glob: bool,
+    cidr: bool,
+}
+
+impl<'a> HostPattern<'a> {
+    fn new(rule: &BypassRule<'a>, syntax: &Syntax) -> HostPattern<'a> {
+        let normalized = bypass::normalize_domain(rule.host);
+        let skip = usize::from(syntax.names == NameMatching::SuffixOrSubdomains && normalized.starts_with("*."));
+        let pattern = &normalized[skip..];
+        let glob = pattern.contains(['*', '?']) && (syntax.wildcards || (syntax.match_all && pattern == "*"));
+
+        HostPattern {
+            normalized,
+            skip,
+            glob,
+            cidr: syntax.cidr,
+        }
+    }
+
+    fn pattern(&self) -> &str {
+        &self.normalized[self.skip..]
+    }
+
+    fn ip(&self) -> Option<IpAddr> {
+        bypass::parse_ip(self.pattern()).filter(|_| !self.glob)
+    }
+
+    fn cidr(&self) -> Option<IpCidr> {
+        IpCidr::parse(self.pattern()).filter(|_| self.cidr && !self.glob && self.ip().is_none())
+    }
+
+    fn ipv4_wildcard(&self) -> Option<Ipv4Wildcard> {
+        Ipv4Wildcard::parse(self.pattern()).filter(|_| self.glob)
+    }
+
+    fn is_ip_rule(&self) -> bool {
+        self.ip().is_some() || self.cidr().is_some() || self.ipv4_wildcard().is_some()
+    }
+
+    /// Match an IP address against the IP, CIDR and IPv4 wildcard rules.
+    fn matches_ip(&self, ip: IpAddr) -> bool {
+        match (self.glob, ip) {
+            (true, IpAddr::V4(ip)) => self.ipv4_wildcard().is_some_and(|wildcard| wildcard.matches(ip)),
+            (true, IpAddr::V6(_)) => false,
+            (false, ip) => self.ip() == Some(ip) || self.cidr().is_some_and(|cidr| cidr.contains(ip)),
+        }
+    }
+
+    /// Match a normalized host, `ip` being the address it is the literal of, the same way the lookup tables
+    /// of a [`BypassMatcher`] do.
+    fn matches(&self, host: &str, ip: Option<IpAddr>, names: NameMatching) -> bool {
+        let pattern = self.pattern();
+
+        if ip.is_some_and(|ip| self.matches_ip(ip)) {
+            return true;
+        }
+
+        if self.glob {
+            return bypass::matches_glob(pattern, host);
+        }
+
+        match (names, pattern.strip_prefix('.')) {
+            (NameMatching::Exact, _) => host == pattern,
+            // Only the IP rules apply to IP literals.
+            _ if ip.is_some() => false,
+            (NameMatching::SuffixOrSubdomains, Some(parent)) if !parent.is_empty() => {
+                host.strip_suffix(parent).is_some_and(|rest| rest.ends_with('.'))
+            }
+            _ => bypass::matches_domain_suffix(pattern, host),
+        }
+    }
+}
+
+/// A glob pattern that can not be stored in one of the lookup tables.
+#[derive(Debug, Clone)]
+struct Glob {
+    pattern: String,
+    restriction: Restriction,
+    /// The pattern is also stored in the domain trie, so it only needs to be checked for IP literals.
+    in_trie: bool,
+}
+
+/// A bypass list compiled from a [`ProxyConfig`] into lookup tables: a hash of exact hosts, a reversed-label
+/// suffix trie for domains and a table of subnets.
+///
+/// Building the matcher does all of the parsing up front, so that looking up an already parsed [`Url`]
+/// with [`BypassMatcher::use_proxy_for_url`] does not allocate. Decisions are the same as the ones made
+/// by [`ProxyConfig::use_proxy_for_address`].
+#[derive(Debug, Clone, Default)]
+pub struct BypassMatcher {
+    exclude_simple: bool,
+    local: bool,
+    loopback: bool,
+    exact_hosts: HashMap<String, Vec<Restriction>>,
+    exact_ips: HashMap<IpAddr, Vec<Restriction>>,
+    domains: DomainTrie,
+    networks: Vec<(IpCidr, Restriction)>,
+    ipv4_wildcards: Vec<(Ipv4Wildcard, Restriction)>,
+    globs: Vec<Glob>,
+}
+
+impl BypassMatcher {
+    pub fn new(config: &ProxyConfig) -> BypassMatcher {
+        let syntax = Syntax::new(config.dialect, config.emulate_dialect);
+
+        let mut matcher = BypassMatcher {
+            exclude_simple: config.exclude_simple,
+            loopback: config.loopback == LoopbackPolicy::Bypass,
+            ..Default::default()
+        };
+
+        let mut entries: Vec<&str> = config.whitelist.iter().map(String::as_str).collect();
+        entries.sort_unstable();
+
+        for (rank, entry) in entries.into_iter().enumerate() {
+            match BypassEntry::parse(entry, &syntax) {
+                BypassEntry::Local => matcher.local = true,
+                BypassEntry::SubtractLoopback => matcher.loopback = false,
+                BypassEntry::Rule(rule) => matcher.add_rule(entry, rank, &rule, &syntax),
+            }
+        }
+
+        matcher
+    }
+
+    fn add_rule(&mut self, entry: &str, rank: usize, rule: &BypassRule<'_>, syntax: &Syntax) {
+        let restriction = Restriction::new(entry, rank, rule);
+        let host_pattern = HostPattern::new(rule, syntax);
+        let pattern = host_pattern.pattern();
+
+        if host_pattern.glob {
+            if let Some(wildcard) = host_pattern.ipv4_wildcard() {
+                self.ipv4_wildcards.push((wildcard, restriction.clone()));
+            }
+
+            let parent = pattern.strip_prefix("*.").filter(|parent| !parent.contains(['*', '?']));
+            if let Some(parent) = parent {
+                self.domains.node_mut(parent).subdomains.push(restriction.clone());
+            }
+
+            self.globs.push(Glob {
+                pattern: pattern.to_owned(),
+                restriction,
+                in_trie: parent.is_some(),
+            });
+            return;
+        }
+
+        if let Some(ip) = host_pattern.ip() {
+            self.exact_ips.entry(ip).or_default().push(restriction.clone());
+        } else if let Some(cidr) = host_pattern.cidr() {
+            self.networks.push((cidr, restriction.clone()));
+        }
+
+        match (syntax.names, pattern.strip_prefix('.')) {
+            (NameMatching::Exact, _) => self
+                .exact_hosts
+                .entry(pattern.to_owned())
+                .or_default()
+                .push(restriction),
+            (NameMatching::SuffixOrSubdomains, Some(parent)) if !parent.is_empty() => {
+                self.domains.node_mut(parent).subdomains.push(restriction);
+            }
+            (_, parent) => {
+                let domain = parent.unwrap_or(pattern);
+                if !domain.is_empty() {
+                    self.domains.node_mut(domain).domain.push(restriction);
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if the proxy should be used for `address`, which is either a URL or a host with an optional
+    /// port, such as `localhost:3000`.
+    pub fn use_proxy_for_address(&self, address: &str) -> bool {
+        self.bypass_reason_for_address(address).is_none()
+    }
+
+    /// Returns `true` if the proxy should be used for `url`.
+    pub fn use_proxy_for_url(&self, url: &Url) -> bool {
+        with_url_target(url, |target| self.find_bypass(target).is_none())
+    }
+
+    /// Returns why `address`, which is either a URL or a host with an optional port, bypasses the proxy, or `None`
+    /// if the proxy should be used.
+    pub fn bypass_reason_for_address(&self, address: &str) -> Option<BypassReason> {
+        with_address_target(address, |target| self.find_bypass(target).map(BypassReason::from))
+    }
+
+    /// Returns why `url` bypasses the proxy, or `None` if the proxy should be used.
+    pub fn bypass_reason(&self, url: &Url) -> Option<BypassReason> {
+        with_url_target(url, |target| self.find_bypass(target).map(BypassReason::from))
+    }
+
+    /// Same as [`BypassMatcher::use_proxy_for_address`], but also bypasses the proxy for host names resolving
+    /// to an address matched by an IP rule, see [`BypassMatcher::bypass_reason_with_resolver`].
+    pub fn use_proxy_for_address_with_resolver<R>(&self, address: &str, resolver: &R) -> bool
+    where
+        R: Resolver + ?Sized,
+    {
+        self.bypass_reason_for_address_with_resolver(address, resolver)
+            .is_none()
+    }
+
+    /// Same as [`BypassMatcher::bypass_reason_for_address`], but also bypasses the proxy for host names
+    /// resolving to an address matched by an IP rule, see [`BypassMatcher::bypass_reason_with_resolver`].
+    pub fn bypass_reason_for_address_with_resolver<R>(&self, address: &str, resolver: &R) -> Option<BypassReason>
+    where
+        R: Resolver + ?Sized,
+    {
+        with_address_target(address, |target| {
+            self.find_bypass_with_resolver(target, resolver).map(BypassReason::from)
+        })
+    }
+
+    /// Same as [`BypassMatcher::bypass_reason`], but a host name matching no rule is resolved with `resolver`,
+    /// and its addresses are checked against the IP and CIDR rules.
+    ///
+    /// The resolver is only called when the bypass list holds IP rules. Resolution errors are ignored, so the
+    /// decision is then made on the host name alone.
+    pub fn bypass_reason_with_resolver<R>(&self, url: &Url, resolver: &R) -> Option<BypassReason>
+    where
+        R: Resolver + ?Sized,
+    {
+        with_url_target(url, |target| {
+            self.find_bypass_with_resolver(target, resolver).map(BypassReason::from)
+        })
+    }
+
+    fn find_bypass_with_resolver<R>(&self, target: &Target<'_>, resolver: &R) -> Option<Bypass<'_>>
+    where
+        R: Resolver + ?Sized,
+    {
+        if let Some(bypass) = self.find_bypass(target) {
+            return Some(bypass);
+        }
+
+        let has_ip_rules = !self.exact_ips.is_empty() || !self.networks.is_empty() || !self.ipv4_wildcards.is_empty();
+        if !has_ip_rules || target.host.is_empty() || bypass::parse_ip(target.host).is_some() {
+            return None;
+        }
+
+        resolver.resolve(target.host).ok()?.into_iter().find_map(|address| {
+            self.find_ip(address.to_canonical(), target)
+                .map(|restriction| Bypass::Resolved(address, &restriction.entry))
+        })
+    }
+
+    fn find_bypass(&self, target: &Target<'_>) -> Option<Bypass<'_>> {
+        let host = target.host;
+        let ip = bypass::parse_ip(host);
+
+        // Simple hostnames (no dots), from either `exclude_simple` or the "<local>" token.
+        if bypass::is_simple_hostname(host) {
+            if self.exclude_simple {
+                return Some(Bypass::ExcludeSimple);
+            }
+
+            if self.local {
+                return Some(Bypass::Local);
+            }
+        }
+
+        if self.loopback && bypass::is_loopback(host) {
+            return Some(Bypass::Loopback);
+        }
+
+        let exact = self
+            .exact_hosts
+            .get(host)
+            .and_then(|restrictions| find_permitted(restrictions, target));
+        let named = match ip {
+            Some(ip) => self.find_ip(ip, target),
+            None => self.domains.find(host, target),
+        };
+        let found = first_ranked([exact, named]);
+
+        // Globs are in rank order, so only the ones before the entry already found need to be checked.
+        let glob = self
+            .globs
+            .iter()
+            .take_while(|glob| found.is_none_or(|found| glob.restriction.rank < found.rank))
+            .find(|glob| {
+                (ip.is_some() || !glob.in_trie)
+                    && glob.restriction.permits(target)
+                    && bypass::matches_glob(&glob.pattern, host)
+            })
+            .map(|glob| &glob.restriction);
+
+        first_ranked([found, glob]).map(|restriction| Bypass::Rule(&restriction.entry))
+    }
+
+    fn find_ip(&self, ip: IpAddr, target: &Target<'_>) -> Option<&Restriction> {
+        let exact = self
+            .exact_ips
+            .get(&ip)
+            .and_then(|restrictions| find_permitted(restrictions, target));
+
+        let network = self
+            .networks
+            .iter()
+            .find(|(cidr, restriction)| cidr.contains(ip) && restriction.permits(target))
+            .map(|(_, restriction)| restriction);
+
+        let wildcard = match ip {
+            IpAddr::V4(ip) => self
+                .ipv4_wildcards
+                .iter()
+                .find(|(wildcard, restriction)| wildcard.matches(ip) && restriction.permits(target))
+                .map(|(_, restriction)| restriction),
+            IpAddr::V6(_) => None,
+        };
+
+        first_ranked([exact, network, wildcard])
+    }
+}
+
+/// The bypass list of a [`ProxyConfig`] matched entry by entry, for one-off decisions that are not worth
+/// building the lookup tables of a [`BypassMatcher`] for. Decisions are the same.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BypassScan<'a> {
+    config: &'a ProxyConfig,
+    syntax: Syntax,
+}
+
+impl<'a> BypassScan<'a> {
+    pub(crate) fn new(config: &'a ProxyConfig) -> BypassScan<'a> {
+        BypassScan {
+            config,
+            syntax: Syntax::new(config.dialect, config.emulate_dialect),
+        }
+    }
+
+    pub(crate) fn use_proxy_for_address(&self, address: &str) -> bool {
+        with_address_target(address, |target| self.find_bypass(target).is_none())
+    }
+
+    pub(crate) fn use_proxy_for_url(&self, url: &Url) -> bool {
+        with_url_target(url, |target| self.find_bypass(target).is_none())
+    }
+
+    pub(crate) fn bypass_reason(&self, url: &Url) -> Option<BypassReason> {
+        with_url_target(url, |target| self.find_bypass(target).map(BypassReason::from))
+    }
+
+    pub(crate) fn use_proxy_for_address_with_resolver<R>(&self, address: &str, resolver: &R) -> bool
+    where
+        R: Resolver + ?Sized,
+    {
+        with_address_target(address, |target| {
+            self.find_bypass_with_resolver(target, resolver).is_none()
+        })
+    }
+
+    pub(crate) fn bypass_reason_with_resolver<R>(&self, url: &Url, resolver: &R) -> Option<BypassReason>
+    where
+        R: Resolver + ?Sized,
+    {
+        with_url_target(url, |target| {
+            self.find_bypass_with_resolver(target, resolver).map(BypassReason::from)
+        })
+    }
+
+    /// The rules of the whitelist permitting `target`, with their entry.
+    fn rules<'t>(&self, target: &'t Target<'_>) -> impl Iterator<Item = (&'a str, BypassRule<'a>)> + use<'a, 't> {
+        let syntax = self.syntax;
+        self.config
+            .whitelist
+            .iter()
+            .filter_map(move |entry| match BypassEntry::parse(entry, &syntax) {
+                BypassEntry::Rule(rule) if rule.permits(target) => Some((entry.as_str(), rule)),
+                _ => None,
+            })
+    }
+
+    fn find_bypass(&self, target: &Target<'_>) -> Option<Bypass<'a>> {
+        let host = target.host;
+        let ip = bypass::parse_ip(host);
+        let simple = bypass::is_simple_hostname(host);
+
+        if simple && self.config.exclude_simple {
+            return Some(Bypass::ExcludeSimple);
+        }
+
+        let tokens = |token: &str| self.syntax.tokens && self.config.whitelist.contains(token);
+        let local = simple && tokens("<local>");
+        let loopback = self.config.loopback == LoopbackPolicy::Bypass && !tokens("<-loopback>");
+
+        if local {
+            return Some(Bypass::Local);
+        }
+
+        if loopback && bypass::is_loopback(host) {
+            return Some(Bypass::Loopback);
+        }
+
+        // When several entries match, the first one in byte order is reported, as `BypassMatcher` does.
+        let mut found: Option<&'a str> = None;
+        for (entry, rule) in self.rules(target) {
+            if found.is_none_or(|found| entry < found)
+                && HostPattern::new(&rule, &self.syntax).matches(host, ip, self.syntax.names)
+            {
+                found = Some(entry);
+            }
+        }
+
+        found.map(Bypass::Rule)
+    }
+
+    fn find_bypass_with_resolver<R>(&self, target: &Target<'_>, resolver: &R) -> Option<Bypass<'a>>
+    where
+        R: Resolver + ?Sized,
+    {
+        if let Some(bypass) = self.find_bypass(target) {
+            return Some(bypass);
+        }
+
+        if target.host.is_empty() || bypass::parse_ip(target.host).is_some() {
+            return None;
+        }
+
+        let mut ip_rules: Vec<(&str, HostPattern<'_>)> = self
+            .rules(target)
+            .map(|(entry, rule)| (entry, HostPattern::new(&rule, &self.syntax)))
+            .filter(|(_, pattern)| pattern.is_ip_rule())
+            .collect();
+        if ip_rules.is_empty() {
+            return None;
+        }
+        ip_rules.sort_unstable_by_key(|(entry, _)| *entry);
+
+        resolver.resolve(target.host).ok()?.into_iter().find_map(|address| {
+            let ip = address.to_canonical();
+            ip_rules
+                .iter()
+                .find(|(_, pattern)| pattern.matches_ip(ip))
+                .map(|(entry, _)| Bypass::Resolved(address, entry))
+        })
+    }
+}
+
+/// Build the normalized target for `address`, which is either a URL or a host with an optional port, and pass it
+/// to `f`.
+fn with_address_target<R>(address: &str, f: impl FnOnce(&Target<'_>) -> R) -> R {
+    // Without `://`, the address is a host, since `Url` reads `localhost:3000` as a URL of scheme `localhost`.
+    let (host, port) = match address.contains("://") {
+        true => match Url::parse(address) {
+            Ok(url) if url.has_host() => return with_url_target(&url, f),
+            _ => (address, None),
+        },
+        false => bypass::split_host_port(address),
+    };
+
+    let host = bypass::normalize_host(host);
+    f(&Target {
+        host: &host,
+        port,
+        scheme: None,
+    })
+}
+
+/// Build the normalized target for `url` and pass it to `f`, without allocating for the common case of a
+/// lowercase ASCII host or an IP address.
+fn with_url_target<R>(url: &Url, f: impl FnOnce(&Target<'_>) -> R) -> R {
+    // Large enough for the textual form of any IPv4 or IPv6 address.
+    let mut buf = [0u8; 64];
+
+    let host = match url.host() {
+        Some(Host::Domain(domain)) if bypass::parse_ip(domain).is_none() => bypass::normalize_domain(domain),
+        Some(Host::Domain(domain)) => Cow::Borrowed(format_ip(bypass::parse_ip(domain), &mut buf)),
+        Some(Host::Ipv4(ip)) => Cow::Borrowed(format_ip(Some(IpAddr::V4(ip)), &mut buf)),
+        Some(Host::Ipv6(ip)) => Cow::Borrowed(format_ip(Some(IpAddr::V6(ip)), &mut buf)),
+        None => {
+            let host = bypass::normalize_host(url.as_str());
+            return f(&Target {
+                host: &host,
+                port: None,
+                scheme: None,
+            });
+        }
+    };
+
+    f(&Target {
+        host: &host,
+        port: url.port_or_known_default(),
+        scheme: Some(url.scheme()),
+    })
+}
+
+/// Write the canonical textual form of an IP address into `buf`.
+fn format_ip(ip: Option<IpAddr>, buf: &mut [u8]) -> &str {
+    let Some(ip) = ip else {
+        return "";
+    };
+
+    let mut cursor = Cursor::new(&mut *buf);
+    let len = match write!(cursor, "{}", ip.to_canonical()) {
+        Ok(()) => usize::try_from(cursor.position()).unwrap_or_default(),
+        Err(_) => 0,
+    };
+
+    str::from_utf8(&buf[..len]).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::io;
+    use std::net::IpAddr;
+
+    use url::Url;
+
+    use super::BypassMatcher;
+    use crate::{BypassDialect, BypassReason, ProxyConfig};
+
+    fn proxy_config(whitelist: &[&str], dialect: BypassDialect) -> ProxyConfig {
+        ProxyConfig {
+            whitelist: whitelist.iter().map(|s| (*s).to_owned()).collect(),
+            dialect,
+            ..Default::default()
+        }
+    }
+
+    /// Check the compiled matcher against the decision made by the `ProxyConfig` itself.
+    fn assert_bypass(config: &ProxyConfig, address: &str, expected_bypass: bool) {
+        let matcher = BypassMatcher::new(config);
+        assert_eq!(
+            !matcher.use_proxy_for_address(address),
+            expected_bypass,
+            "address: {address}"
+        );
+        assert_eq!(
+            !config.use_proxy_for_address(address),
+            expected_bypass,
+            "address: {address}"
+        );
+        if let Ok(url) = Url::parse(address)
+            && url.has_host()
+        {
+            assert_eq!(!matcher.use_proxy_for_url(&url), expected_bypass, "url: {address}");
+        }
+    }
+
+    #[test]
+    fn test_exact_and_subdomain_rules() {
+        let config = proxy_config(
+            &["www.devolutions.net", "*.microsoft.com", "*apple.com", "host?.corp"],
+            BypassDialect::Native,
+        );
+
+        assert_bypass(&config, "http://www.devolutions.net", true);
+        assert_bypass(&config, "http://devolutions.net", false);
+        assert_bypass(&config, "http://www.microsoft.com", true);
+        assert_bypass(&config, "http://a.b.microsoft.com", true);
+        assert_bypass(&config, "http://microsoft.com", false);
+        assert_bypass(&config, "http://www.microsoft.com.fun", false);
+        assert_bypass(&config, "http://test.apple.com", true);
+        assert_bypass(&config, "http://apple.com", true);
+        assert_bypass(&config, "http://host1.corp", true);
+        assert_bypass(&config, "http://host12.corp", false);
+        assert_bypass(&config, "http://anything.xyz", false);
+        assert_bypass(&config, "WWW.Devolutions.NET", true);
+    }
+
+    #[test]
+    fn test_domain_suffix_rules() {
+        let config = proxy_config(&["example.com", ".corp.example", "10.0.0.1"], BypassDialect::Curl);
+
+        assert_bypass(&config, "http://example.com", true);
+        assert_bypass(&config, "http://sub.example.com", true);
+        assert_bypass(&config, "http://a.b.example.com", true);
+        assert_bypass(&config, "http://corp.example", true);
+        assert_bypass(&config, "http://app.corp.example", true);
+        assert_bypass(&config, "http://notexample.com", false);
+        assert_bypass(&config, "http://example.com.evil", false);
+        assert_bypass(&config, "http://10.0.0.1", true);
+        assert_bypass(&config, "http://110.0.0.1", false);
+    }
+
+    #[test]
+    fn test_go_dialect() {
+        let config = proxy_config(&["example.com", ".corp.example", "*.lab.example"], BypassDialect::Go);
+
+        assert_bypass(&config, "http://example.com", true);
+        assert_bypass(&config, "http://www.example.com", true);
+        assert_bypass(&config, "http://corp.example", false);
+        assert_bypass(&config, "http://app.corp.example", true);
+        assert_bypass(&config, "http://lab.example", false);
+        assert_bypass(&config, "http://app.lab.example", true);
+    }
+
+    #[test]
+    fn test_dialect_emulation() {
+        let whitelist = [
+            "*",
+            "example.com:8443",
+            "https://github.com",
+            "10.0.0.0/8",
+            "*.corp.example",
+            "192.168.*",
+            "<local>",
+        ];
+        let lenient = proxy_config(&whitelist[1..], BypassDialect::Curl);
+        assert_bypass(&lenient, "https://example.com:8443", true);
+        assert_bypass(&lenient, "https://github.com", true);
+        assert_bypass(&lenient, "http://10.1.2.3", true);
+        assert_bypass(&lenient, "http://app.corp.example", true);
+        assert_bypass(&lenient, "http://192.168.1.1", true);
+        assert_bypass(&lenient, "http://intranet", true);
+
+        let mut curl = lenient.clone();
+        curl.emulate(BypassDialect::Curl);
+        assert_bypass(&curl, "https://example.com:8443", false);
+        assert_bypass(&curl, "example.com:8443", false);
+        assert_bypass(&curl, "https://github.com", false);
+        assert_bypass(&curl, "http://10.1.2.3", true);
+        assert_bypass(&curl, "http://app.corp.example", false);
+        assert_bypass(&curl, "http://192.168.1.1", false);
+        assert_bypass(&curl, "http://intranet", false);
+
+        let mut python = lenient.clone();
+        python.emulate(BypassDialect::Python);
+        assert_bypass(&python, "https://example.com:8443", true);
+        assert_bypass(&python, "https://example.com", false);
+        assert_bypass(&python, "http://10.1.2.3", false);
+
+        let mut windows = lenient;
+        windows.emulate(BypassDialect::Windows);
+        assert_bypass(&windows, "https://example.com:8443", false);
+        assert_bypass(&windows, "example.com:8443", false);
+        assert_bypass(&windows, "https://github.com", true);
+        assert_bypass(&windows, "http://10.1.2.3", false);
+        assert_bypass(&windows, "http://app.corp.example", true);
+        assert_bypass(&windows, "http://192.168.1.1", true);
+        assert_bypass(&windows, "http://intranet", true);
+
+        // A lone "*" is understood by every dialect but Wget.
+        let mut all = proxy_config(&whitelist[..1], BypassDialect::Native);
+        for dialect in [
+            BypassDialect::Curl,
+            BypassDialect::Go,
+            BypassDialect::Python,
+            BypassDialect::Windows,
+        ] {
+            all.emulate(dialect);
+            assert_bypass(&all, "http://anything.xyz", true);
+        }
+        all.emulate(BypassDialect::Wget);
+        assert_bypass(&all, "http://anything.xyz", false);
+    }
+
+    #[test]
+    fn test_ip_rules() {
+        let config = proxy_config(
+            &[
+                "192.168.16.0/24",
+                "172.16.10.*",
+                "::1",
+                "[2001:db8::10]",
+                "fe80::/10",
+                "*.0.0.9",
+                "fd00::*",
+            ],
+            BypassDialect::Native,
+        );
+
+        assert_bypass(&config, "http://192.168.16.1", true);
+        assert_bypass(&config, "http://192.168.17.1", false);
+        assert_bypass(&config, "http://172.16.10.7", true);
+        assert_bypass(&config, "http://172.16.11.7", false);
+        assert_bypass(&config, "http://[2001:db8::10]", true);
+        assert_bypass(&config, "2001:DB8::10", true);
+        assert_bypass(&config, "http://[fe80::1234]", true);
+        assert_bypass(&config, "fe80::1234%eth0", true);
+        assert_bypass(&config, "http://[::ffff:192.168.16.1]", true);
+        assert_bypass(&config, "http://10.0.0.9", true);
+        assert_bypass(&config, "http://[fd00::1]", true);
+        assert_bypass(&config, "http://[0:0::1]", true);
+        assert_bypass(&config, "http://[2001:db8::11]", false);
+    }
+
+    #[test]
+    fn test_restricted_rules() {
+        let config = proxy_config(
+            &[
+                "example.com:8443",
+                "https://github.com/",
+                "http://*.corp.example:8080",
+                "10.0.0.0/8:8080",
+            ],
+            BypassDialect::Native,
+        );
+
+        assert_bypass(&config, "https://example.com:8443", true);
+        assert_bypass(&config, "https://example.com", false);
+        assert_bypass(&config, "example.com", false);
+        assert_bypass(&config, "example.com:8443", true);
+        assert_bypass(&config, "[2001:db8::1]:8443", false);
+        assert_bypass(&config, "https://github.com/devolutions", true);
+        assert_bypass(&config, "http://github.com", false);
+        assert_bypass(&config, "http://app.corp.example:8080", true);
+        assert_bypass(&config, "https://app.corp.example:8080", false);
+        assert_bypass(&config, "http://10.1.2.3:8080", true);
+        assert_bypass(&config, "http://10.1.2.3", false);
+    }
+
+    #[test]
+    fn test_special_tokens() {
+        let mut config = proxy_config(&["<local>"], BypassDialect::Windows);
+        config.exclude_simple = false;
+
+        assert_bypass(&config, "http://intranet", true);
+        assert_bypass(&config, "http://intranet.corp", false);
+        assert_bypass(&config, "http://[2001:db8::1]", false);
+        assert_bypass(&config, "http://localhost:3000", true);
+        assert_bypass(&config, "http://127.0.0.1", true);
+
+        config.whitelist = HashSet::from(["<-loopback>".to_owned()]);
+        assert_bypass(&config, "http://127.0.0.1", false);
+        assert_bypass(&config, "http://[::1]", false);
+        assert_bypass(&config, "http://localhost:3000", false);
+    }
+
+    #[test]
+    fn test_internationalized_domain_names() {
+        let config = proxy_config(
+            &["bücher.example", "*.MÜNCHEN.de", "xn--caf-dma.example"],
+            BypassDialect::Native,
+        );
+
+        assert_bypass(&config, "http://bücher.example", true);
+        assert_bypass(&config, "http://xn--bcher-kva.example", true);
+        assert_bypass(&config, "Bücher.example", true);
+        assert_bypass(&config, "http://www.münchen.de", true);
+        assert_bypass(&config, "http://www.xn--mnchen-3ya.de", true);
+        assert_bypass(&config, "http://café.example", true);
+        assert_bypass(&config, "café.example", true);
+        assert_bypass(&config, "socks5://b%C3%BCcher.example", true);
+        assert_bypass(&config, "http://bucher.example", false);
+
+        let env_config = proxy_config(&["münchen.de"], BypassDialect::Curl);
+        assert_bypass(&env_config, "http://www.münchen.de", true);
+        assert_bypass(&env_config, "http://xn--mnchen-3ya.de", true);
+    }
+
+    #[test]
+    fn test_bypass_everything() {
+        for dialect in [BypassDialect::Native, BypassDialect::Curl, BypassDialect::Windows] {
+            let config = proxy_config(&["*"], dialect);
+            assert_bypass(&config, "http://anything.xyz", true);
+            assert_bypass(&config, "https://www.example.com:8443", true);
+            assert_bypass(&config, "http://10.1.2.3", true);
+            assert_bypass(&config, "http://[2001:db8::1]", true);
+            assert_bypass(&config, "example.com", true);
+        }
+
+        // A scheme-qualified "*" only applies to that scheme.
+        let config = proxy_config(&["http://*"], BypassDialect::Native);
+        assert_bypass(&config, "http://anything.xyz", true);
+        assert_bypass(&config, "https://anything.xyz", false);
+    }
+
+    #[test]
+    fn test_bypass_reason() {
+        let mut config = proxy_config(
+            &["<local>", "*.example.com", "10.0.0.0/8:8080", "Bücher.example"],
+            BypassDialect::Windows,
+        );
+        let matcher = BypassMatcher::new(&config);
+        let reason = |address| matcher.bypass_reason_for_address(address);
+
+        assert_eq!(reason("http://intranet"), Some(BypassReason::Local));
+        assert_eq!(reason("http://127.0.0.1:3000"), Some(BypassReason::Loopback));
+        assert_eq!(
+            reason("http://www.example.com"),
+            Some(BypassReason::Rule("*.example.com".to_owned()))
+        );
+        assert_eq!(
+            reason("http://10.1.2.3:8080"),
+            Some(BypassReason::Rule("10.0.0.0/8:8080".to_owned()))
+        );
+        assert_eq!(
+            reason("http://xn--bcher-kva.example"),
+            Some(BypassReason::Rule("Bücher.example".to_owned()))
+        );
+        assert_eq!(reason("http://10.1.2.3"), None);
+        assert_eq!(reason("http://example.org"), None);
+
+        config.exclude_simple = true;
+        let matcher = BypassMatcher::new(&config);
+        assert_eq!(
+            matcher.bypass_reason(&Url::parse("http://intranet").unwrap()),
+            Some(BypassReason::ExcludeSimple)
+        );
+    }
+
+    #[test]
+    fn test_bypass_reason_overlapping_rules() {
+        let whitelist = [
+            "w*.example.com",
+            "*w.example.com",
+            "10.1.0.0/16",
+            "10.0.0.0/8",
+            "*.corp.example",
+            "app.corp.example",
+        ];
+
+        // Whitelists built anew iterate in a different order, and the first entry in byte order is reported.
+        for _ in 0..20 {
+            let config = proxy_config(&whitelist, BypassDialect::Curl);
+            let matcher = BypassMatcher::new(&config);
+            let reason = |address| {
+                let url = Url::parse(address).unwrap();
+                let reason = matcher.bypass_reason(&url);
+                assert_eq!(config.explain_proxy_for_url(&url).bypass, reason, "url: {address}");
+                reason
+            };
+
+            assert_eq!(
+                reason("http://ww.example.com"),
+                Some(BypassReason::Rule("*w.example.com".to_owned()))
+            );
+            assert_eq!(
+                reason("http://10.1.2.3"),
+                Some(BypassReason::Rule("10.0.0.0/8".to_owned()))
+            );
+            assert_eq!(
+                reason("http://app.corp.example"),
+                Some(BypassReason::Rule("*.corp.example".to_owned()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolver() {
+        let hosts: HashMap<&str, Vec<IpAddr>> = HashMap::from([
+            (
+                "intranet.corp",
+                vec!["192.0.2.1".parse().unwrap(), "10.1.2.3".parse().unwrap()],
+            ),
+            ("mapped.corp", vec!["::ffff:10.9.9.9".parse().unwrap()]),
+            ("public.example", vec!["203.0.113.7".parse().unwrap()]),
+        ]);
+        let resolver = |host: &str| {
+            hosts
+                .get(host)
+                .cloned()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        };
+
+        let config = proxy_config(&["10.0.0.0/8", "web.example:443"], BypassDialect::Native);
+        let matcher = BypassMatcher::new(&config);
+
+        assert!(matcher.use_proxy_for_address("http://intranet.corp"));
+        assert!(!matcher.use_proxy_for_address_with_resolver("http://intranet.corp", &resolver));
+        assert!(!matcher.use_proxy_for_address_with_resolver("INTRANET.corp", &resolver));
+        assert!(!matcher.use_proxy_for_address_with_resolver("http://mapped.corp", &resolver));
+        assert!(matcher.use_proxy_for_address_with_resolver("http://public.example", &resolver));
+        assert!(matcher.use_proxy_for_address_with_resolver("http://unknown.example", &resolver));
+        assert_eq!(
+            matcher.bypass_reason_with_resolver(&Url::parse("http://intranet.corp/").unwrap(), &resolver),
+            Some(BypassReason::Resolved {
+                address: "10.1.2.3".parse().unwrap(),
+                entry: "10.0.0.0/8".to_owned(),
+            })
+        );
+        for address in ["http://intranet.corp", "http://mapped.corp", "http://public.example"] {
+            let url = Url::parse(address).unwrap();
+            assert_eq!(
+                config.explain_proxy_for_url_with_resolver(&url, &resolver).bypass,
+                matcher.bypass_reason_with_resolver(&url, &resolver),
+                "url: {address}"
+            );
+        }
+
+        // Rules on names still win, and IP literals are never resolved.
+        let failing = |_: &str| -> io::Result<Vec<IpAddr>> { panic!("unexpected lookup") };
+        assert!(!matcher.use_proxy_for_address_with_resolver("https://web.example", &failing));
+        assert!(matcher.use_proxy_for_address_with_resolver("http://192.0.2.1", &failing));
+
+        // Without IP rules, there is nothing to resolve names for.
+        let config = proxy_config(&["web.example"], BypassDialect::Native);
+        assert!(BypassMatcher::new(&config).use_proxy_for_address_with_resolver("http://intranet.corp", &failing));
+        assert!(config.use_proxy_for_address_with_resolver("http://intranet.corp", &failing));
+    }
+
+    #[test]
+    fn test_non_special_schemes() {
+        let config = proxy_config(&["proxy.example", "10.0.0.0/8"], BypassDialect::Native);
+
+        // Hosts of non-special schemes are neither lowercased nor parsed as IP addresses by `Url`.
+        assert_bypass(&config, "socks5://PROXY.example", true);
+        assert_bypass(&config, "socks5://pr%4Fxy.example", true);
+        assert_bypass(&config, "socks5://10.1.1.1:1080", true);
+        assert_bypass(&config, "socks5://11.1.1.1:1080", false);
+    }
+}
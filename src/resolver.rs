@@ -0,0 +1,37 @@
+//! Name resolution for matching host names against the IP rules of a bypass list.
+
+use std::io;
+use std::net::{IpAddr, ToSocketAddrs};
+
+/// Resolves a host name to its IP addresses, for the `*_with_resolver` lookups of [`BypassMatcher`] and
+/// [`ProxyConfig`].
+///
+/// Host names are passed in their normalized form: lowercase, with internationalized names in ASCII.
+///
+/// Closures taking a `&str` and returning `io::Result<Vec<IpAddr>>` are resolvers too, which is convenient
+/// for tests and for plugging in an asynchronous resolver's cache.
+///
+/// [`BypassMatcher`]: crate::BypassMatcher
+/// [`ProxyConfig`]: crate::ProxyConfig
+pub trait Resolver {
+    fn resolve(&self, host: &str) -> io::Result<Vec<IpAddr>>;
+}
+
+impl<F> Resolver for F
+where
+    F: Fn(&str) -> io::Result<Vec<IpAddr>>,
+{
+    fn resolve(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        self(host)
+    }
+}
+
+/// The resolver of the operating system, as used by [`std::net::ToSocketAddrs`]. Lookups block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn resolve(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        Ok((host, 0).to_socket_addrs()?.map(|address| address.ip()).collect())
+    }
+}
@@ -0,0 +1,198 @@
+//! Choosing one proxy out of the ordered candidates configured for a destination.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use url::Url;
+
+use super::{ProxyCandidate, ProxyEndpoint};
+
+/// How a [`ProxySelector`] picks among the available candidates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SelectionStrategy {
+    /// The first available candidate, in the configured order.
+    #[default]
+    Failover,
+    /// Each available candidate in turn.
+    RoundRobin,
+    /// The same available candidate for every URL of a host, so that connections to a host share a proxy.
+    HashByHost,
+}
+
+/// Picks a candidate from the list returned by
+/// [`ProxyConfig::get_proxy_candidates_for_url`](crate::ProxyConfig::get_proxy_candidates_for_url).
+///
+/// Proxies reported as unavailable with [`ProxySelector::mark_unavailable`] are skipped until they are
+/// reported as available again. When every candidate is unavailable, all of them are considered, so that a
+/// destination is never left without a way to reach it. Direct connections are always available.
+#[derive(Debug, Default)]
+pub struct ProxySelector {
+    strategy: SelectionStrategy,
+    next: AtomicUsize,
+    unavailable: Mutex<HashSet<ProxyEndpoint>>,
+}
+
+impl ProxySelector {
+    pub fn new(strategy: SelectionStrategy) -> ProxySelector {
+        ProxySelector {
+            strategy,
+            ..Default::default()
+        }
+    }
+
+    pub fn strategy(&self) -> SelectionStrategy {
+        self.strategy
+    }
+
+    /// Skip `endpoint` in later selections, for instance after failing to connect to it.
+    pub fn mark_unavailable(&self, endpoint: &ProxyEndpoint) {
+        self.lock_unavailable().insert(endpoint.clone());
+    }
+
+    pub fn mark_available(&self, endpoint: &ProxyEndpoint) {
+        self.lock_unavailable().remove(endpoint);
+    }
+
+    /// Pick the candidate to use for `url`, or `None` if `candidates` is empty.
+    pub fn select<'a>(&self, candidates: &'a [ProxyCandidate], url: &Url) -> Option<&'a ProxyCandidate> {
+        let available: Vec<&ProxyCandidate> = {
+            let unavailable = self.lock_unavailable();
+            candidates
+                .iter()
+                .filter(|candidate| {
+                    candidate
+                        .endpoint()
+                        .is_none_or(|endpoint| !unavailable.contains(endpoint))
+                })
+                .collect()
+        };
+        let available = if available.is_empty() {
+            candidates.iter().collect()
+        } else {
+            available
+        };
+        if available.is_empty() {
+            return None;
+        }
+
+        let index = match self.strategy {
+            SelectionStrategy::Failover => 0,
+            SelectionStrategy::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed) % available.len(),
+            SelectionStrategy::HashByHost => {
+                let hash = fnv1a(url.host_str().unwrap_or_default().to_ascii_lowercase().as_bytes());
+                usize::try_from(hash % available.len() as u64).unwrap_or_default()
+            }
+        };
+
+        available.get(index).copied()
+    }
+
+    fn lock_unavailable(&self) -> std::sync::MutexGuard<'_, HashSet<ProxyEndpoint>> {
+        // The set is always left consistent, so a panic while it was locked does not matter.
+        self.unavailable.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The 64-bit FNV-1a hash, which unlike the standard library's hasher is stable across releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::{ProxySelector, SelectionStrategy};
+    use crate::{ProxyCandidate, ProxyEndpoint};
+
+    fn candidates() -> Vec<ProxyCandidate> {
+        vec![
+            "proxy1:8080".parse::<ProxyEndpoint>().unwrap().into(),
+            "proxy2:8080".parse::<ProxyEndpoint>().unwrap().into(),
+            ProxyCandidate::Direct,
+        ]
+    }
+
+    fn url(value: &str) -> Url {
+        Url::parse(value).unwrap()
+    }
+
+    #[test]
+    fn test_failover() {
+        let candidates = candidates();
+        let selector = ProxySelector::new(SelectionStrategy::Failover);
+        let first = candidates[0].endpoint().unwrap();
+        let second = candidates[1].endpoint().unwrap();
+
+        assert_eq!(
+            selector.select(&candidates, &url("http://a.example")),
+            Some(&candidates[0])
+        );
+
+        selector.mark_unavailable(first);
+        assert_eq!(
+            selector.select(&candidates, &url("http://a.example")),
+            Some(&candidates[1])
+        );
+
+        selector.mark_unavailable(second);
+        assert_eq!(
+            selector.select(&candidates, &url("http://a.example")),
+            Some(&ProxyCandidate::Direct)
+        );
+
+        selector.mark_available(first);
+        assert_eq!(
+            selector.select(&candidates, &url("http://a.example")),
+            Some(&candidates[0])
+        );
+
+        // When nothing is available, the configured order applies again.
+        let proxies = &candidates[..2];
+        selector.mark_unavailable(first);
+        assert_eq!(selector.select(proxies, &url("http://a.example")), Some(&candidates[0]));
+        assert_eq!(selector.select(&[], &url("http://a.example")), None);
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let candidates = candidates();
+        let selector = ProxySelector::new(SelectionStrategy::RoundRobin);
+        let selected: Vec<_> = (0..4)
+            .map(|_| selector.select(&candidates, &url("http://a.example")).unwrap())
+            .collect();
+
+        assert_eq!(
+            selected,
+            vec![&candidates[0], &candidates[1], &candidates[2], &candidates[0]]
+        );
+
+        selector.mark_unavailable(candidates[1].endpoint().unwrap());
+        assert_ne!(
+            selector.select(&candidates, &url("http://a.example")),
+            Some(&candidates[1])
+        );
+    }
+
+    #[test]
+    fn test_hash_by_host() {
+        let candidates = candidates();
+        let selector = ProxySelector::new(SelectionStrategy::HashByHost);
+
+        let selected = selector.select(&candidates, &url("http://a.example/one")).unwrap();
+        assert_eq!(
+            selector.select(&candidates, &url("https://A.example/two")),
+            Some(selected)
+        );
+
+        // Hosts spread over the candidates.
+        let spread: std::collections::HashSet<_> = (0..32)
+            .filter_map(|i| selector.select(&candidates, &url(&format!("http://host{i}.example"))))
+            .collect();
+        assert_eq!(spread.len(), candidates.len());
+    }
+}
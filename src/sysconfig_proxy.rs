@@ -9,7 +9,9 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 
-use super::{Error, ProxyConfig, Result};
+use super::{
+    BypassDialect, ConfigSource, Diagnostic, Error, ProxyConfig, ProxyEndpoint, ProxyMode, ProxyProtocol, Result,
+};
 
 /// Extract proxy information from /etc/sysconfig/proxy if the file is available
 /// and formatted correctly.
@@ -20,7 +22,11 @@ pub(crate) fn get_proxy_config() -> Result<Option<ProxyConfig>> {
 /// The same as `get_proxy_config()` but this function expects a file's path as an
 /// argument.
 fn get_proxy_config_from_file<P: AsRef<Path>>(config_file: P) -> Result<Option<ProxyConfig>> {
-    let mut proxy_config: ProxyConfig = Default::default();
+    let mut proxy_config = ProxyConfig {
+        source: ConfigSource::Sysconfig,
+        dialect: BypassDialect::Curl,
+        ..Default::default()
+    };
     if !config_file.as_ref().exists() {
         return Ok(None);
     }
@@ -28,31 +34,46 @@ fn get_proxy_config_from_file<P: AsRef<Path>>(config_file: P) -> Result<Option<P
     if let Some(enabled) = map.get("PROXY_ENABLED") {
         match enabled.as_str() {
             "yes" => (), // Continue running this function.
-            "no" => return Ok(None),
+            "no" => {
+                proxy_config.mode = ProxyMode::Direct;
+                return Ok(Some(proxy_config));
+            }
             _ => return Err(Error::InvalidConfig), // Consider all other values as illegal.
         }
     } else {
         return Err(Error::InvalidConfig); // Missing PROXY_ENABLED directive.
     }
 
-    // Determine the proxies.
-    let schemes = ["HTTP", "HTTPS", "FTP"];
-    for scheme in schemes.iter() {
+    // Determine the proxies. An empty value leaves the scheme unset, and other values that are not a proxy
+    // address are reported.
+    let schemes = [
+        ("HTTP", "http", ProxyProtocol::Http),
+        ("HTTPS", "https", ProxyProtocol::Http),
+        ("FTP", "ftp", ProxyProtocol::Http),
+        ("SOCKS", "*", ProxyProtocol::Socks5),
+    ];
+    for (scheme, proxy_key, protocol) in schemes {
         let key = format!("{}_PROXY", scheme);
-        if let Some(proxy) = map.get(&key) {
-            // Check if ${SCHEME}_PROXY is defined.
-            let scheme = scheme.to_lowercase();
-            proxy_config.proxies.insert(scheme, proxy.clone());
+        let Some(value) = map.get(&key).filter(|value| !value.trim().is_empty()) else {
+            continue;
+        };
+
+        match ProxyEndpoint::parse_with_protocol(value, protocol) {
+            Ok(proxy) => {
+                proxy_config.proxies.insert(proxy_key.to_owned(), vec![proxy.into()]);
+            }
+            Err(_) => proxy_config.diagnostics.push(Diagnostic::invalid_proxy(&key, value)),
         }
     }
 
+    // Without a usable proxy, the next source is tried, as when the other sources have no proxy settings.
+    if proxy_config.proxies.is_empty() {
+        proxy_config.mode = ProxyMode::Unset;
+    }
+
     // Determine the list of domains that should not be requested through the proxy.
     if let Some(no_proxy) = map.get("NO_PROXY") {
-        for no_proxy_url in no_proxy.split(",") {
-            proxy_config
-                .whitelist
-                .insert(no_proxy_url.trim().to_owned().to_lowercase());
-        }
+        proxy_config.whitelist.extend(proxy_config.dialect.split_list(no_proxy));
     }
 
     Ok(Some(proxy_config))
@@ -117,7 +138,7 @@ mod tests {
     use std::io::Write;
 
     use self::tempfile::NamedTempFile;
-    use super::{get_proxy_config_from_file, read_key_value_pairs_from_file, strip_after_quote};
+    use super::{Diagnostic, ProxyMode, get_proxy_config_from_file, read_key_value_pairs_from_file, strip_after_quote};
 
     /// Write a string to a temporary file.
     fn spit(contents: &str) -> NamedTempFile {
@@ -167,7 +188,9 @@ HTTPS_PROXY="https://1.2.3.4:8000""##,
 HTTPS_PROXY="https://1.2.3.4:8000"
 PROXY_ENABLED="no""##,
         );
-        assert!(get_proxy_config_from_file(file.path()).is_ok());
+        let config = get_proxy_config_from_file(file.path()).unwrap().unwrap();
+        assert_eq!(config.mode, ProxyMode::Direct);
+        assert!(config.proxies.is_empty());
 
         let file = spit(
             r##"HTTP_PROXY="http://1.2.3.4"
@@ -175,8 +198,54 @@ HTTPS_PROXY="https://1.2.3.4:8000"
 PROXY_ENABLED="yes""##,
         );
         let config = get_proxy_config_from_file(file.path()).unwrap().unwrap();
-        assert_eq!(&config.proxies["http"], "http://1.2.3.4");
-        assert_eq!(&config.proxies["https"], "https://1.2.3.4:8000");
+        assert_eq!(config.mode, ProxyMode::Manual);
+        assert_eq!(config.proxies["http"][0].to_string(), "http://1.2.3.4:80");
+        assert_eq!(config.proxies["https"][0].to_string(), "https://1.2.3.4:8000");
+        assert!(config.diagnostics.is_empty());
+
+        let file = spit(
+            r##"HTTP_PROXY="http://1.2.3.4:99999"
+HTTPS_PROXY=""
+PROXY_ENABLED="yes""##,
+        );
+        let config = get_proxy_config_from_file(file.path()).unwrap().unwrap();
+        assert_eq!(config.mode, ProxyMode::Unset);
+        assert!(config.proxies.is_empty());
+        assert_eq!(
+            config.diagnostics,
+            vec![Diagnostic::InvalidProxy {
+                name: "HTTP_PROXY".to_owned(),
+                value: "http://1.2.3.4:99999".to_owned(),
+            }]
+        );
+
+        let file = spit(
+            r##"HTTP_PROXY=""
+NO_PROXY="localhost"
+PROXY_ENABLED="yes""##,
+        );
+        let config = get_proxy_config_from_file(file.path()).unwrap().unwrap();
+        assert_eq!(config.mode, ProxyMode::Unset);
+        assert!(config.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_socks_proxy() {
+        let file = spit(
+            r##"PROXY_ENABLED="yes"
+HTTP_PROXY="http://1.2.3.4"
+SOCKS_PROXY="socks5h://5.6.7.8:1081""##,
+        );
+        let config = get_proxy_config_from_file(file.path()).unwrap().unwrap();
+        assert_eq!(config.proxies["*"][0].to_string(), "socks5h://5.6.7.8:1081");
+        assert!(config.proxies["*"][0].endpoint().unwrap().protocol.remote_dns());
+
+        let file = spit(
+            r##"PROXY_ENABLED="yes"
+SOCKS_PROXY="5.6.7.8""##,
+        );
+        let config = get_proxy_config_from_file(file.path()).unwrap().unwrap();
+        assert_eq!(config.proxies["*"][0].to_string(), "socks5://5.6.7.8:1080");
     }
 
     #[test]
@@ -202,6 +271,20 @@ PROXY_ENABLED="yes""##,
         }
     }
 
+    #[test]
+    fn test_no_proxy_wildcard() {
+        let file = spit(
+            r##"PROXY_ENABLED="yes"
+HTTP_PROXY="http://192.168.0.1"
+HTTPS_PROXY="http://192.168.0.1"
+NO_PROXY="*""##,
+        );
+        let config = get_proxy_config_from_file(file.path()).unwrap().unwrap();
+        assert!(!config.use_proxy_for_address("http://example.com"));
+        assert!(!config.use_proxy_for_address("https://www.example.org:8443"));
+        assert!(!config.use_proxy_for_address("http://192.168.10.20"));
+    }
+
     #[test]
     fn test_unquote() {
         assert_eq!(strip_after_quote("foo"), "foo");
@@ -223,11 +306,22 @@ NO_PROXY="localhost, 127.0.0.1"
 "##,
         );
         let config = get_proxy_config_from_file(file.path()).unwrap().unwrap();
-        assert_eq!(&config.proxies["http"], "http://192.168.0.1");
-        assert_eq!(&config.proxies["https"], "http://192.168.0.1");
-        assert_eq!(&config.proxies["ftp"], "http://192.168.0.1");
+        assert_eq!(config.proxies["http"][0].to_string(), "http://192.168.0.1:80");
+        assert_eq!(config.proxies["https"][0].to_string(), "http://192.168.0.1:80");
+        assert_eq!(config.proxies["ftp"][0].to_string(), "http://192.168.0.1:80");
         assert!(config.whitelist.contains("localhost"));
         assert!(config.whitelist.contains("127.0.0.1"));
+
+        let file = spit(
+            r##"PROXY_ENABLED="yes"
+HTTP_PROXY="http://192.168.0.1"
+NO_PROXY=".example.com, internal.net""##,
+        );
+        let config = get_proxy_config_from_file(file.path()).unwrap().unwrap();
+        assert!(!config.use_proxy_for_address("http://example.com"));
+        assert!(!config.use_proxy_for_address("http://www.example.com"));
+        assert!(!config.use_proxy_for_address("http://app.internal.net"));
+        assert!(config.use_proxy_for_address("http://example.org"));
     }
 
     #[test]
@@ -10,7 +10,10 @@ use windows_sys::core::PWSTR;
 use winreg::RegKey;
 use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
 
-use super::{ProxyConfig, Result};
+use super::{
+    BypassDialect, ConfigSource, Diagnostic, ProxyCandidate, ProxyConfig, ProxyEndpoint, ProxyMode, ProxyProtocol,
+    Result,
+};
 
 const REG_POLICIES: &str = r"Software\Policies\Microsoft\Windows\CurrentVersion\Internet Settings";
 const REG_SETTINGS: &str = r"Software\Microsoft\Windows\CurrentVersion\Internet Settings";
@@ -23,6 +26,15 @@ enum AutoconfigType {
     None,
 }
 
+fn windows_config(mode: ProxyMode) -> ProxyConfig {
+    ProxyConfig {
+        source: ConfigSource::Windows,
+        dialect: BypassDialect::Windows,
+        mode,
+        ..Default::default()
+    }
+}
+
 /// Converts a null-terminated wide string pointer to a Rust String.
 ///
 /// # Safety
@@ -50,14 +62,10 @@ unsafe fn pwstr_null_to_string(wide: PWSTR) -> Option<String> {
 }
 
 // Bypass list is semi-colon delimited.
-// The special value "<local>" means all local addresses.
+// The special value "<local>" means all simple hostnames, and "<-loopback>" removes the implicit loopback bypass.
+// Both are kept as-is and interpreted when matching.
 fn parse_bypass_list(bypass_list: &str) -> Vec<String> {
-    bypass_list
-        .split(';')
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .map(|s| s.to_lowercase())
-        .collect()
+    BypassDialect::Windows.split_list(bypass_list)
 }
 
 // Proxy server list can be specified in three ways:
@@ -65,25 +73,52 @@ fn parse_bypass_list(bypass_list: &str) -> Vec<String> {
 // 1. A semicolon-separated mapping of list scheme to url/port pairs, e.g., "http=proxy1:8080;ftp=ftpproxy".
 // 2. A single URI with optional port to use for all URLs, e.g., "proxy2:8080".
 // 3. The special "direct://" value, which will make all connections not use a proxy.
-fn parse_proxy_list(proxy_list: &str) -> HashMap<String, String> {
-    let mut result = HashMap::new();
-
-    if proxy_list == "direct://" {
+// Entries repeated for a scheme are its candidates in order, and a "direct://" entry among them is a direct
+// connection candidate. "socks=" entries are SOCKS4 proxies. Entries that are not a proxy address are skipped,
+// and reported in `diagnostics` as entries of the `name` setting.
+fn parse_proxy_list(
+    proxy_list: &str,
+    name: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> HashMap<String, Vec<ProxyCandidate>> {
+    let mut result: HashMap<String, Vec<ProxyCandidate>> = HashMap::new();
+
+    if proxy_list.trim() == "direct://" {
         return result;
     }
 
     let proxies = proxy_list.split(';').map(|s| s.trim()).filter(|s| !s.is_empty());
+    let mut socks = Vec::new();
 
     for proxy in proxies {
-        let split: Vec<&str> = proxy.splitn(2, '=').collect();
-
-        if split.len() == 1 {
-            result.insert("*".into(), split[0].into());
+        let (scheme, value) = match proxy.split_once('=') {
+            Some((scheme, value)) => (scheme.to_lowercase(), value),
+            None => ("*".to_owned(), proxy),
+        };
+        let protocol = match scheme.as_str() {
+            "socks" => ProxyProtocol::Socks4,
+            _ => ProxyProtocol::Http,
+        };
+
+        let candidate = if value.trim().eq_ignore_ascii_case("direct://") {
+            ProxyCandidate::Direct
+        } else if let Ok(endpoint) = ProxyEndpoint::parse_with_protocol(value, protocol) {
+            endpoint.into()
         } else {
-            result.insert(split[0].to_lowercase(), split[1].into());
+            diagnostics.push(Diagnostic::invalid_proxy(name, proxy));
+            continue;
+        };
+
+        match scheme.as_str() {
+            "socks" => socks.push(candidate),
+            _ => result.entry(scheme).or_default().push(candidate),
         }
     }
 
+    if !socks.is_empty() {
+        result.entry("*".into()).or_insert(socks);
+    }
+
     result
 }
 
@@ -125,20 +160,30 @@ fn win_inet_get_autoconfig_type(connections: RegKey) -> AutoconfigType {
     AutoconfigType::None
 }
 
+// A disabled proxy, or the "direct://" proxy server, explicitly asks for direct connections. Settings without
+// a ProxyEnable value at all are left to the next source, and so are proxy servers without a valid entry, which
+// are reported with `ProxyMode::Unset`.
 fn win_inet_get_proxy_config(internet_settings: RegKey) -> Option<ProxyConfig> {
-    if internet_settings.get_value("ProxyEnable").unwrap_or(0u32) != 1 {
-        return None;
+    match internet_settings.get_value("ProxyEnable") {
+        Ok(1u32) => (),
+        Ok(_) => return Some(windows_config(ProxyMode::Direct)),
+        Err(_) => return None,
     }
 
     if let Ok(proxy_server) = internet_settings.get_value("ProxyServer") {
         let proxy_server: String = proxy_server;
-        let proxy_list = parse_proxy_list(&proxy_server);
+        if proxy_server.trim() == "direct://" {
+            return Some(windows_config(ProxyMode::Direct));
+        }
+
+        let mut proxy_config = windows_config(ProxyMode::Manual);
+        let proxy_list = parse_proxy_list(&proxy_server, "ProxyServer", &mut proxy_config.diagnostics);
 
         if proxy_list.is_empty() {
-            return None;
+            proxy_config.mode = ProxyMode::Unset;
+            return (!proxy_config.diagnostics.is_empty()).then_some(proxy_config);
         }
 
-        let mut proxy_config: ProxyConfig = Default::default();
         proxy_config.proxies.extend(proxy_list);
 
         if let Ok(proxy_override) = internet_settings.get_value("ProxyOverride") {
@@ -147,10 +192,6 @@ fn win_inet_get_proxy_config(internet_settings: RegKey) -> Option<ProxyConfig> {
             proxy_config.whitelist.extend(bypass_list);
         }
 
-        if proxy_config.whitelist.contains("<local>") {
-            proxy_config.exclude_simple = true;
-        }
-
         return Some(proxy_config);
     }
 
@@ -158,10 +199,12 @@ fn win_inet_get_proxy_config(internet_settings: RegKey) -> Option<ProxyConfig> {
 }
 
 fn win_inet_get_current_user_config() -> Option<ProxyConfig> {
-    if let Ok(key) = RegKey::predef(HKEY_CURRENT_USER).open_subkey(REG_CONNECTIONS)
-        && win_inet_get_autoconfig_type(key) != AutoconfigType::None
-    {
-        return None;
+    if let Ok(key) = RegKey::predef(HKEY_CURRENT_USER).open_subkey(REG_CONNECTIONS) {
+        match win_inet_get_autoconfig_type(key) {
+            AutoconfigType::Pac => return Some(windows_config(ProxyMode::AutoConfig)),
+            AutoconfigType::Wpad => return Some(windows_config(ProxyMode::AutoDetect)),
+            AutoconfigType::None => (),
+        }
     }
 
     if let Ok(key) = RegKey::predef(HKEY_CURRENT_USER).open_subkey(REG_SETTINGS) {
@@ -172,10 +215,12 @@ fn win_inet_get_current_user_config() -> Option<ProxyConfig> {
 }
 
 fn win_inet_get_local_machine_config() -> Option<ProxyConfig> {
-    if let Ok(key) = RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey(REG_CONNECTIONS)
-        && win_inet_get_autoconfig_type(key) != AutoconfigType::None
-    {
-        return None;
+    if let Ok(key) = RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey(REG_CONNECTIONS) {
+        match win_inet_get_autoconfig_type(key) {
+            AutoconfigType::Pac => return Some(windows_config(ProxyMode::AutoConfig)),
+            AutoconfigType::Wpad => return Some(windows_config(ProxyMode::AutoDetect)),
+            AutoconfigType::None => (),
+        }
     }
 
     if let Ok(key) = RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey(REG_SETTINGS) {
@@ -199,13 +244,18 @@ fn win_http_get_default_config() -> Option<ProxyConfig> {
     // SAFETY: lpszProxy comes from the Windows API and is either null or points to a valid
     // null-terminated wide string that remains valid for the duration of this function.
     let proxy_server = unsafe { pwstr_null_to_string(proxy_info.lpszProxy) };
-    let proxy_list = parse_proxy_list(&proxy_server.unwrap_or_default());
+    let mut proxy_config = windows_config(ProxyMode::Manual);
+    let proxy_list = parse_proxy_list(
+        &proxy_server.unwrap_or_default(),
+        "WinHTTP proxy",
+        &mut proxy_config.diagnostics,
+    );
 
     if proxy_list.is_empty() {
-        return None;
+        proxy_config.mode = ProxyMode::Unset;
+        return (!proxy_config.diagnostics.is_empty()).then_some(proxy_config);
     }
 
-    let mut proxy_config: ProxyConfig = Default::default();
     proxy_config.proxies.extend(proxy_list);
 
     // SAFETY: lpszProxyBypass comes from the Windows API and is either null or points to a valid
@@ -217,36 +267,64 @@ fn win_http_get_default_config() -> Option<ProxyConfig> {
         proxy_config.whitelist.extend(bypass_list);
     }
 
-    if proxy_config.whitelist.contains("<local>") {
-        proxy_config.exclude_simple = true;
-    }
-
     Some(proxy_config)
 }
 
-pub(crate) fn get_proxy_config() -> Result<Option<ProxyConfig>> {
-    let win_inet_user_proxy = win_inet_get_current_user_config();
-
-    if (!win_inet_is_per_user() || win_inet_user_proxy.is_none())
-        && let Some(proxy_config) = win_inet_get_local_machine_config()
-    {
-        return Ok(Some(proxy_config));
+// The first of the settings with proxies or an auto-config wins. `ProxyEnable` is 0 on most installs, so a
+// disabled proxy only means direct connections when none of the other settings has a proxy, such as the
+// machine-wide WinHTTP proxy set with `netsh winhttp set proxy`. Settings with nothing usable are only
+// returned for their diagnostics, which are kept whatever the answer.
+fn select_proxy_config(configs: impl IntoIterator<Item = Option<ProxyConfig>>) -> Option<ProxyConfig> {
+    let mut fallback: Option<ProxyConfig> = None;
+    let mut diagnostics = Vec::new();
+    for mut proxy_config in configs.into_iter().flatten() {
+        diagnostics.append(&mut proxy_config.diagnostics);
+        match proxy_config.mode {
+            ProxyMode::Unset if fallback.is_some() => {}
+            ProxyMode::Direct if fallback.as_ref().is_some_and(|config| config.mode == ProxyMode::Direct) => {}
+            ProxyMode::Unset | ProxyMode::Direct => fallback = Some(proxy_config),
+            _ => {
+                proxy_config.diagnostics = diagnostics;
+                return Some(proxy_config);
+            }
+        }
     }
 
-    if let Some(proxy_config) = win_inet_user_proxy {
-        return Ok(Some(proxy_config));
-    }
+    fallback.map(|mut proxy_config| {
+        proxy_config.diagnostics = diagnostics;
+        proxy_config
+    })
+}
 
-    if let Some(proxy_config) = win_http_get_default_config() {
-        return Ok(Some(proxy_config));
-    }
+pub(crate) fn get_proxy_config() -> Result<Option<ProxyConfig>> {
+    let sources: [fn() -> Option<ProxyConfig>; 3] = match win_inet_is_per_user() {
+        true => [
+            win_inet_get_current_user_config,
+            win_inet_get_local_machine_config,
+            win_http_get_default_config,
+        ],
+        false => [
+            win_inet_get_local_machine_config,
+            win_inet_get_current_user_config,
+            win_http_get_default_config,
+        ],
+    };
 
-    Ok(None)
+    Ok(select_proxy_config(sources.into_iter().map(|source| source())))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{parse_bypass_list, parse_proxy_list};
+    use super::{parse_bypass_list, parse_proxy_list, select_proxy_config, windows_config};
+    use crate::{Diagnostic, ProxyCandidate, ProxyConfig, ProxyMode};
+
+    fn manual(proxy_list: &str) -> ProxyConfig {
+        let mut proxy_config = windows_config(ProxyMode::Manual);
+        proxy_config
+            .proxies
+            .extend(parse_proxy_list(proxy_list, "ProxyServer", &mut Vec::new()));
+        proxy_config
+    }
 
     #[test]
     fn parse_exceptions_test() {
@@ -257,17 +335,95 @@ mod tests {
 
     #[test]
     fn parse_proxies_test() {
-        let hm = parse_proxy_list("http=1.2.3.4:80");
+        let hm = parse_proxy_list("http=1.2.3.4:80", "ProxyServer", &mut Vec::new());
         assert_eq!(1, hm.len());
-        assert_eq!("1.2.3.4:80", &hm["http"]);
+        assert_eq!("http://1.2.3.4:80", hm["http"][0].to_string());
 
-        let hm = parse_proxy_list("1.2.3.4;https=http://8.8.8.8");
+        let hm = parse_proxy_list("1.2.3.4;https=http://8.8.8.8", "ProxyServer", &mut Vec::new());
         assert_eq!(2, hm.len());
-        assert_eq!("1.2.3.4", &hm["*"]);
-        assert_eq!("http://8.8.8.8", &hm["https"]);
-
-        let hm = parse_proxy_list("http=1.2.3.4;https=8.8.8.8 ;  http=9.8.7.6:123");
+        assert_eq!("http://1.2.3.4:80", hm["*"][0].to_string());
+        assert_eq!("http://8.8.8.8:80", hm["https"][0].to_string());
+
+        let hm = parse_proxy_list(
+            "http=1.2.3.4;https=8.8.8.8 ;  http=9.8.7.6:123",
+            "ProxyServer",
+            &mut Vec::new(),
+        );
+        assert_eq!(2, hm.len());
+        let http: Vec<String> = hm["http"].iter().map(|candidate| candidate.to_string()).collect();
+        assert_eq!(vec!["http://1.2.3.4:80", "http://9.8.7.6:123"], http);
+
+        let hm = parse_proxy_list(
+            "1.2.3.4:8080;5.6.7.8:8080;direct://;https=DIRECT://",
+            "ProxyServer",
+            &mut Vec::new(),
+        );
+        assert_eq!(
+            vec![
+                ProxyCandidate::Proxy("1.2.3.4:8080".parse().unwrap()),
+                ProxyCandidate::Proxy("5.6.7.8:8080".parse().unwrap()),
+                ProxyCandidate::Direct,
+            ],
+            hm["*"]
+        );
+        assert_eq!(vec![ProxyCandidate::Direct], hm["https"]);
+        assert!(parse_proxy_list(" direct:// ", "ProxyServer", &mut Vec::new()).is_empty());
+
+        let hm = parse_proxy_list("http=1.2.3.4:80;socks=5.6.7.8:1080", "ProxyServer", &mut Vec::new());
         assert_eq!(2, hm.len());
-        assert_eq!("9.8.7.6:123", &hm["http"]);
+        assert_eq!("socks4://5.6.7.8:1080", hm["*"][0].to_string());
+
+        let hm = parse_proxy_list("socks=socks5://5.6.7.8", "ProxyServer", &mut Vec::new());
+        assert_eq!("socks5://5.6.7.8:1080", hm["*"][0].to_string());
+
+        let mut diagnostics = Vec::new();
+        let hm = parse_proxy_list("http=1.2.3.4:99999;https=5.6.7.8", "ProxyServer", &mut diagnostics);
+        assert_eq!(1, hm.len());
+        assert_eq!(
+            vec![Diagnostic::InvalidProxy {
+                name: "ProxyServer".to_owned(),
+                value: "http=1.2.3.4:99999".to_owned(),
+            }],
+            diagnostics
+        );
+    }
+
+    #[test]
+    fn select_proxy_config_test() {
+        let direct = || Some(windows_config(ProxyMode::Direct));
+
+        // A disabled WinINet proxy, the default, does not hide the WinHTTP proxy.
+        let proxy_config = select_proxy_config([direct(), None, Some(manual("http=1.2.3.4:80"))]).unwrap();
+        assert_eq!(proxy_config.mode, ProxyMode::Manual);
+        assert_eq!("http://1.2.3.4:80", proxy_config.proxies["http"][0].to_string());
+
+        let proxy_config = select_proxy_config([direct(), direct(), None]).unwrap();
+        assert_eq!(proxy_config.mode, ProxyMode::Direct);
+
+        let proxy_config = select_proxy_config([
+            Some(windows_config(ProxyMode::AutoConfig)),
+            Some(manual("1.2.3.4")),
+            None,
+        ])
+        .unwrap();
+        assert_eq!(proxy_config.mode, ProxyMode::AutoConfig);
+
+        assert!(select_proxy_config([None, None, None]).is_none());
+
+        // Settings without a valid proxy are reported, and the ones after them still apply.
+        let mut invalid = windows_config(ProxyMode::Unset);
+        invalid
+            .diagnostics
+            .push(Diagnostic::invalid_proxy("ProxyServer", "1.2.3.4:99999"));
+        let proxy_config = select_proxy_config([Some(invalid.clone()), direct(), None]).unwrap();
+        assert_eq!(proxy_config.mode, ProxyMode::Direct);
+        assert_eq!(proxy_config.diagnostics, invalid.diagnostics);
+
+        let proxy_config = select_proxy_config([Some(invalid.clone()), None, Some(manual("1.2.3.4"))]).unwrap();
+        assert_eq!(proxy_config.mode, ProxyMode::Manual);
+        assert_eq!(proxy_config.diagnostics, invalid.diagnostics);
+
+        let proxy_config = select_proxy_config([None, Some(invalid.clone()), None]).unwrap();
+        assert_eq!(proxy_config.mode, ProxyMode::Unset);
     }
 }
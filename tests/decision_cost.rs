@@ -0,0 +1,70 @@
+//! The decision methods of `ProxyConfig` match the whitelist entry by entry: building the lookup tables of a
+//! `BypassMatcher` for each call made them many times slower.
+
+#![allow(clippy::unwrap_used, unused_crate_dependencies)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use proxy_cfg::{BypassMatcher, ProxyConfig, ProxyEndpoint};
+use url::Url;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+struct CountingAllocator;
+
+// SAFETY: every call is forwarded to the system allocator.
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::alloc`.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::dealloc`, and `ptr` comes from `System`.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn proxy_config(entries: usize) -> ProxyConfig {
+    let mut config = ProxyConfig::default();
+    let proxy: ProxyEndpoint = "proxy.example:8080".parse().unwrap();
+    config.proxies.insert("http".to_owned(), vec![proxy.into()]);
+    config.whitelist = (0..entries)
+        .map(|i| match i % 4 {
+            0 => format!("host{i}.example.com"),
+            1 => format!("*.corp{i}.example"),
+            2 => format!("10.{}.0.0/16", i % 256),
+            _ => format!("https://web{i}.example:8443"),
+        })
+        .collect();
+    config
+}
+
+#[test]
+fn test_decisions_do_not_compile_the_whitelist() {
+    let url = Url::parse("http://www.example.org").unwrap();
+    let small = proxy_config(8);
+    let large = proxy_config(500);
+
+    let decide = |config: &ProxyConfig| {
+        allocations(|| {
+            assert!(config.get_proxy_for_url(&url).is_some());
+            assert!(config.use_proxy_for_address("www.example.org"));
+        })
+    };
+
+    // Compiling allocates for every entry, deciding does not.
+    assert!(allocations(|| drop(BypassMatcher::new(&large))) > 500);
+    assert_eq!(decide(&small), decide(&large));
+}